@@ -0,0 +1,530 @@
+//! Exports the API a module provides as AsyncAPI document and its types as
+//! standalone JSON Schemas, for tools which do not speak the EVerest YAML
+//! dialect.
+
+use crate::codegen::{module_name, parse_yaml, Output, SearchRoot, Source, TypeRef, YamlRepo};
+use crate::schema::types::{TypeBase, TypeEnum};
+use crate::schema::{Manifest, Type};
+use anyhow::{bail, Context, Result};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+/// The dialect of the JSON Schemas. The framework validates against draft 7
+/// and the AsyncAPI schema format is a superset of it.
+const JSON_SCHEMA_DIALECT: &str = "http://json-schema.org/draft-07/schema#";
+
+/// The default MQTT topic prefix of the framework, see
+/// `MQTT_EVEREST_PREFIX`.
+const MQTT_PREFIX: &str = "everest";
+
+/// The framework subscribes and publishes everything with QoS 2, regardless
+/// of the `qos` of the variables.
+const MQTT_QOS: i64 = 2;
+
+/// Converts [Type]s into JSON Schema and collects the named types they
+/// reference.
+struct Converter {
+    /// Whether `$ref`s point to the standalone schema files instead of the
+    /// components of the AsyncAPI document.
+    files: bool,
+    refs: BTreeSet<TypeRef>,
+}
+
+/// Returns the key of the named type in the components of the AsyncAPI
+/// document, e.g. `evse_manager.Limits`.
+fn component_name(r: &TypeRef) -> String {
+    format!("{}.{}", r.module_path.join("."), r.type_name)
+}
+
+/// Returns the path of the standalone schema of the named type.
+fn schema_path(r: &TypeRef) -> String {
+    format!("schemas/{}/{}.json", r.module_path.join("/"), r.type_name)
+}
+
+fn type_name(t: &TypeEnum) -> &'static str {
+    match t {
+        TypeEnum::Null => "null",
+        TypeEnum::Boolean(_) => "boolean",
+        TypeEnum::String(_) => "string",
+        TypeEnum::Number(_) => "number",
+        TypeEnum::Integer(_) => "integer",
+        TypeEnum::Array(_) => "array",
+        TypeEnum::Object(_) => "object",
+    }
+}
+
+fn insert(schema: &mut Map<String, Value>, key: &str, value: Option<impl Into<Value>>) {
+    if let Some(value) = value {
+        schema.insert(key.to_string(), value.into());
+    }
+}
+
+impl Converter {
+    fn new(files: bool) -> Self {
+        Self {
+            files,
+            refs: BTreeSet::new(),
+        }
+    }
+
+    /// Returns the `$ref` to `reference`, which appears in the types file
+    /// `scope` or in an interface.
+    fn link(&mut self, reference: &str, scope: Option<&[String]>) -> Result<Value> {
+        let r = TypeRef::from_reference(reference, scope)?;
+        let target = if self.files {
+            // The schema of a type lives in `schemas/<scope>/<Type>.json`.
+            format!(
+                "{}{}/{}.json",
+                "../".repeat(scope.map_or(0, |s| s.len())),
+                r.module_path.join("/"),
+                r.type_name
+            )
+        } else {
+            format!("#/components/schemas/{}", component_name(&r))
+        };
+        self.refs.insert(r);
+        Ok(json!(target))
+    }
+
+    fn convert(&mut self, t: &Type, scope: Option<&[String]>) -> Result<Value> {
+        let mut schema = match &t.arg {
+            TypeBase::Single(t) => self.convert_enum(t, scope)?,
+            TypeBase::Multiple(types) => {
+                let names: Vec<_> = types.iter().map(type_name).collect();
+                Map::from_iter([("type".to_string(), json!(names))])
+            }
+        };
+        insert(&mut schema, "description", t.description.clone());
+        Ok(Value::Object(schema))
+    }
+
+    fn convert_enum(
+        &mut self,
+        t: &TypeEnum,
+        scope: Option<&[String]>,
+    ) -> Result<Map<String, Value>> {
+        let mut schema = Map::new();
+        let reference = match t {
+            TypeEnum::String(args) => args.object_reference.as_ref(),
+            TypeEnum::Object(args) => args.object_reference.as_ref(),
+            _ => None,
+        };
+        match reference {
+            Some(r) => {
+                schema.insert("$ref".to_string(), self.link(r, scope)?);
+            }
+            None => {
+                schema.insert("type".to_string(), json!(type_name(t)));
+            }
+        }
+        match t {
+            TypeEnum::Null => {}
+            TypeEnum::Boolean(args) => insert(&mut schema, "default", args.default),
+            TypeEnum::String(args) => {
+                insert(&mut schema, "pattern", args.pattern.clone());
+                insert(
+                    &mut schema,
+                    "format",
+                    args.format.as_ref().map(|_| "date-time"),
+                );
+                insert(&mut schema, "minLength", args.min_length);
+                insert(&mut schema, "maxLength", args.max_length);
+                insert(&mut schema, "enum", args.enum_items.clone());
+                insert(&mut schema, "default", args.default.clone());
+            }
+            TypeEnum::Number(args) => {
+                insert(&mut schema, "minimum", args.minimum);
+                insert(&mut schema, "maximum", args.maximum);
+                insert(&mut schema, "default", args.default);
+            }
+            TypeEnum::Integer(args) => {
+                insert(&mut schema, "minimum", args.minimum);
+                insert(&mut schema, "maximum", args.maximum);
+                insert(&mut schema, "default", args.default);
+            }
+            TypeEnum::Array(args) => {
+                if let Some(items) = &args.items {
+                    schema.insert("items".to_string(), self.convert(items, scope)?);
+                }
+                insert(&mut schema, "minItems", args.min_items);
+                insert(&mut schema, "maxItems", args.max_items);
+            }
+            TypeEnum::Object(args) => {
+                if args.object_reference.is_some() {
+                    return Ok(schema);
+                }
+                let mut properties = Map::new();
+                for (name, property) in &args.properties {
+                    properties.insert(name.clone(), self.convert(property, scope)?);
+                }
+                if !properties.is_empty() {
+                    schema.insert("properties".to_string(), Value::Object(properties));
+                }
+                let required: BTreeSet<_> = args.required.iter().collect();
+                if !required.is_empty() {
+                    schema.insert("required".to_string(), json!(required));
+                }
+                // The schema default is `true`, but codegen treats a missing
+                // `additionalProperties` as `false`, so we only know about
+                // explicit `true`s.
+                if args.additional_properties {
+                    schema.insert("additionalProperties".to_string(), json!(true));
+                }
+            }
+        }
+        Ok(schema)
+    }
+}
+
+/// Returns the schema of the `{"name": <name>, ...}` envelope the framework
+/// wraps every message into.
+fn envelope(name: &str, kind: Option<&str>, data: Value) -> Value {
+    let mut properties = Map::new();
+    properties.insert("name".to_string(), json!({ "const": name }));
+    let mut required = vec!["name", "data"];
+    if let Some(kind) = kind {
+        properties.insert("type".to_string(), json!({ "const": kind }));
+        required.push("type");
+    }
+    properties.insert("data".to_string(), data);
+    json!({
+        "type": "object",
+        "required": required,
+        "properties": properties,
+    })
+}
+
+/// Returns the schema of the `data` of a command call or result.
+fn cmd_data(payload: &str, schema: Value) -> Value {
+    json!({
+        "type": "object",
+        "required": ["id", payload, "origin"],
+        "properties": {
+            "id": { "type": "string", "description": "Matches the result to the call." },
+            payload: schema,
+            "origin": { "type": "string", "description": "The id of the sending module." },
+        },
+    })
+}
+
+/// Exports the implementations the manifest of `source` provides as
+/// AsyncAPI 3 document `asyncapi.json`: Every implementation gets a `cmd`
+/// and a `var` channel. Commands are request/reply operations on the `cmd`
+/// channel and variables send operations on the `var` channel, both with the
+/// envelopes the framework puts around the payloads. Every type which is
+/// reachable from the interfaces is also written as standalone JSON Schema
+/// `schemas/<types file>/<Type>.json`.
+pub fn asyncapi(source: Source, everest_core: Vec<SearchRoot>) -> Result<Output> {
+    let Source::Manifest(manifest_path) = source else {
+        bail!("Exporting an AsyncAPI document needs a manifest");
+    };
+    let manifest: Manifest = parse_yaml(&manifest_path)?;
+    let module = module_name(&manifest_path)?;
+    let mut yaml_repo = YamlRepo::new(everest_core);
+    let mut converter = Converter::new(false);
+
+    let mut channels = Map::new();
+    let mut operations = Map::new();
+    for (implementation_id, provides) in &manifest.provides {
+        let interface = yaml_repo
+            .get_interface(&provides.interface)
+            .with_context(|| format!("Failed to load the interface of `{implementation_id}`"))?;
+        let parameters = json!({
+            "module_id": { "description": "The id of the module in the EVerest config." },
+        });
+
+        let cmd_channel = format!("{implementation_id}.cmd");
+        let mut cmd_messages = Map::new();
+        for (cmd_name, cmd) in &interface.cmds {
+            let mut args = Map::new();
+            for (arg_name, arg) in &cmd.arguments {
+                args.insert(arg_name.clone(), converter.convert(arg, None)?);
+            }
+            let args = json!({
+                "type": "object",
+                "required": cmd
+                    .arguments
+                    .iter()
+                    .filter(|(_, arg)| !arg.has_default())
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>(),
+                "properties": args,
+                "additionalProperties": false,
+            });
+            let retval = match &cmd.result {
+                Some(result) => converter.convert(result, None)?,
+                None => json!({ "type": "null" }),
+            };
+            let call = format!("{cmd_name}.call");
+            let result = format!("{cmd_name}.result");
+            cmd_messages.insert(
+                call.clone(),
+                json!({
+                    "name": call,
+                    "description": cmd.description,
+                    "payload": envelope(cmd_name, Some("call"), cmd_data("args", args)),
+                }),
+            );
+            cmd_messages.insert(
+                result.clone(),
+                json!({
+                    "name": result,
+                    "payload": envelope(cmd_name, Some("result"), cmd_data("retval", retval)),
+                }),
+            );
+            operations.insert(
+                format!("{implementation_id}.{cmd_name}"),
+                json!({
+                    "action": "receive",
+                    "description": cmd.description,
+                    "channel": { "$ref": format!("#/channels/{cmd_channel}") },
+                    "messages": [
+                        { "$ref": format!("#/channels/{cmd_channel}/messages/{call}") },
+                    ],
+                    "reply": {
+                        "channel": { "$ref": format!("#/channels/{cmd_channel}") },
+                        "messages": [
+                            { "$ref": format!("#/channels/{cmd_channel}/messages/{result}") },
+                        ],
+                    },
+                    "bindings": { "mqtt": { "qos": MQTT_QOS } },
+                }),
+            );
+        }
+        if !cmd_messages.is_empty() {
+            channels.insert(
+                cmd_channel,
+                json!({
+                    "address": format!("{MQTT_PREFIX}/{{module_id}}/{implementation_id}/cmd"),
+                    "description": format!("Calls of and results from the commands of `{}`.", provides.interface),
+                    "parameters": parameters,
+                    "messages": cmd_messages,
+                }),
+            );
+        }
+
+        let var_channel = format!("{implementation_id}.var");
+        let mut var_messages = Map::new();
+        for (var_name, var) in &interface.vars {
+            var_messages.insert(
+                var_name.clone(),
+                json!({
+                    "name": var_name,
+                    "description": var.description,
+                    "payload": envelope(var_name, None, converter.convert(var, None)?),
+                }),
+            );
+            operations.insert(
+                format!("{implementation_id}.{var_name}"),
+                json!({
+                    "action": "send",
+                    "description": var.description,
+                    "channel": { "$ref": format!("#/channels/{var_channel}") },
+                    "messages": [
+                        { "$ref": format!("#/channels/{var_channel}/messages/{var_name}") },
+                    ],
+                    "bindings": { "mqtt": { "qos": MQTT_QOS } },
+                }),
+            );
+        }
+        if !var_messages.is_empty() {
+            channels.insert(
+                var_channel,
+                json!({
+                    "address": format!("{MQTT_PREFIX}/{{module_id}}/{implementation_id}/var"),
+                    "description": format!("The variables of `{}`.", provides.interface),
+                    "parameters": parameters,
+                    "messages": var_messages,
+                }),
+            );
+        }
+    }
+
+    // Resolve the named types, and the ones they reference in turn.
+    let mut types = BTreeMap::new();
+    loop {
+        let pending: Vec<_> = converter
+            .refs
+            .iter()
+            .filter(|r| !types.contains_key(*r))
+            .cloned()
+            .collect();
+        if pending.is_empty() {
+            break;
+        }
+        for r in pending {
+            let data_types = yaml_repo.get_data_types(&r.module_path.join("/"))?;
+            let Some(t) = data_types.types.get(&r.type_name) else {
+                bail!(
+                    "Type `{}` not found in `{}`",
+                    r.type_name,
+                    r.module_path.join("/")
+                );
+            };
+            let scope = Some(r.module_path.as_slice());
+            let schema = converter.convert(t, scope)?;
+            let mut file_schema = Converter::new(true).convert(t, scope)?;
+            if let Value::Object(map) = &mut file_schema {
+                map.insert("$schema".to_string(), json!(JSON_SCHEMA_DIALECT));
+                map.insert("title".to_string(), json!(r.type_name));
+            }
+            types.insert(r, (schema, file_schema));
+        }
+    }
+
+    let mut files = BTreeMap::new();
+    let mut schemas = Map::new();
+    for (r, (schema, file_schema)) in types {
+        files.insert(
+            PathBuf::from(schema_path(&r)),
+            serde_json::to_string_pretty(&file_schema)? + "\n",
+        );
+        schemas.insert(component_name(&r), schema);
+    }
+
+    let mut info = json!({
+        "title": module,
+        "version": "0.0.0",
+        "description": manifest.description,
+        "license": { "name": manifest.metadata.license },
+    });
+    // Manifests usually give the license as URL.
+    if manifest.metadata.license.starts_with("http") {
+        info["license"]["url"] = json!(manifest.metadata.license);
+    }
+    let document = json!({
+        "asyncapi": "3.0.0",
+        "info": info,
+        "defaultContentType": "application/json",
+        "channels": channels,
+        "operations": operations,
+        "components": { "schemas": schemas },
+    });
+    files.insert(
+        PathBuf::from("asyncapi.json"),
+        serde_json::to_string_pretty(&document)? + "\n",
+    );
+
+    let mut input_files = yaml_repo.files().clone();
+    input_files.insert(manifest_path);
+    Ok(Output {
+        files,
+        input_files,
+        warnings: yaml_repo.warnings().to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{roots, tree};
+    use std::path::Path;
+
+    const SETTINGS_TYPES: &str = r#"
+description: Settings
+types:
+  Settings:
+    description: The settings
+    type: object
+    properties:
+      mode:
+        type: string
+        $ref: /settings#/Mode
+  Mode:
+    description: How fast to count
+    type: string
+    enum: [Fast, Slow]
+"#;
+
+    const SETTINGS_INTERFACE: &str = r#"
+description: Counts by settings
+cmds:
+  add:
+    description: Adds to the step
+    arguments:
+      value:
+        description: The value to add
+        type: integer
+        default: 1
+      step:
+        description: The step to add
+        type: integer
+    result:
+      description: The new settings
+      type: object
+      $ref: /settings#/Settings
+vars:
+  settings:
+    description: The current settings
+    type: object
+    $ref: /settings#/Settings
+"#;
+
+    const SETTINGS_MANIFEST: &str = r#"
+description: Counts
+provides:
+  main:
+    interface: counter
+    description: The counter
+metadata:
+  license: https://opensource.org/licenses/Apache-2.0
+  authors: [Jane Doe]
+"#;
+
+    #[test]
+    fn exports_the_provided_api() {
+        let dir = tree(&[
+            ("types/settings.yaml", SETTINGS_TYPES),
+            ("interfaces/counter.yaml", SETTINGS_INTERFACE),
+            ("modules/RsCounter/manifest.yaml", SETTINGS_MANIFEST),
+        ]);
+        let out = asyncapi(
+            Source::Manifest(dir.path().join("modules/RsCounter/manifest.yaml")),
+            roots(&dir),
+        )
+        .unwrap();
+        assert_eq!(
+            out.files.keys().collect::<Vec<_>>(),
+            [
+                "asyncapi.json",
+                "schemas/settings/Mode.json",
+                "schemas/settings/Settings.json"
+            ]
+        );
+        let file =
+            |path: &str| -> Value { serde_json::from_str(&out.files[Path::new(path)]).unwrap() };
+
+        let document = file("asyncapi.json");
+        assert_eq!(document["info"]["title"], "RsCounter");
+        let operations = &document["operations"];
+        // Commands are request/reply, variables are published.
+        assert_eq!(operations["main.add"]["action"], "receive");
+        assert_eq!(
+            operations["main.add"]["reply"]["messages"][0]["$ref"],
+            "#/channels/main.cmd/messages/add.result"
+        );
+        assert_eq!(operations["main.settings"]["action"], "send");
+
+        let call = &document["channels"]["main.cmd"]["messages"]["add.call"]["payload"];
+        let args = &call["properties"]["data"]["properties"]["args"];
+        assert_eq!(args["required"], json!(["step"]));
+        assert_eq!(args["properties"]["value"]["default"], 1);
+        let result = &document["channels"]["main.cmd"]["messages"]["add.result"]["payload"];
+        assert_eq!(
+            result["properties"]["data"]["properties"]["retval"]["$ref"],
+            "#/components/schemas/settings.Settings"
+        );
+        assert_eq!(
+            document["components"]["schemas"]["settings.Mode"]["enum"],
+            json!(["Fast", "Slow"])
+        );
+
+        // The standalone schemas refer to each other by path.
+        let settings = file("schemas/settings/Settings.json");
+        assert_eq!(
+            settings["properties"]["mode"]["$ref"],
+            "../settings/Mode.json"
+        );
+    }
+}
@@ -1,30 +1,309 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
 use argh::FromArgs;
-use everestrs_build::Builder;
+use everestrs_build::{Builder, DocsFormat, GraphFormat};
 use std::path::PathBuf;
 
 #[derive(FromArgs)]
-/// Codegen for EVerest-rs
+/// Codegen for EVerest-rs. Without a subcommand, generates the code for
+/// --manifest, --interface or --emit-types-crate into --out-dir.
 struct Args {
-    /// path to everest-core.
+    /// path to everest-core. Can be repeated, earlier roots take precedence.
     #[argh(option)]
     pub everest_core: Vec<PathBuf>,
 
+    /// installation prefix of EVerest, searched after all --everest-core roots.
+    #[argh(option)]
+    pub prefix: Option<PathBuf>,
+
+    /// a root which only provides names inside a namespace, given as
+    /// `<namespace>=<path>`. Searched after all --everest-core roots.
+    #[argh(option)]
+    pub namespaced_root: Vec<String>,
+
     /// manifest to generate code for
     #[argh(option)]
-    pub manifest: PathBuf,
+    pub manifest: Option<PathBuf>,
+
+    /// generate only the traits and types of this interface instead of the
+    /// code for a manifest. Can be repeated.
+    #[argh(option)]
+    pub interface: Vec<String>,
 
     /// output directory to put the generated code to.
     #[argh(option)]
+    pub out_dir: Option<PathBuf>,
+
+    /// directory with templates overriding the built-in ones.
+    #[argh(option)]
+    pub template_dir: Option<PathBuf>,
+
+    /// pass command arguments as generated structs.
+    #[argh(switch)]
+    pub command_args_structs: bool,
+
+    /// derive integer types from the schema bounds.
+    #[argh(switch)]
+    pub precise_integers: bool,
+
+    /// write one file per types module and interface.
+    #[argh(switch)]
+    pub split_modules: bool,
+
+    /// derive `schemars::JsonSchema` for the generated types.
+    #[argh(switch)]
+    pub json_schema: bool,
+
+    /// implement `proptest::arbitrary::Arbitrary` for the generated types.
+    #[argh(switch)]
+    pub arbitrary: bool,
+
+    /// name of the crate generated with --emit-types-crate to take the types
+    /// from.
+    #[argh(option)]
+    pub types_crate: Option<String>,
+
+    /// visibility of the generated items, e.g. `pub`. Defaults to
+    /// `pub(crate)`.
+    #[argh(option)]
+    pub visibility: Option<String>,
+
+    /// absolute path of the `generated` module, e.g. `crate::foo::generated`
+    /// if the generated code is included into `crate::foo`. Defaults to
+    /// `crate::generated`.
+    #[argh(option)]
+    pub root_module: Option<String>,
+
+    /// generate a crate with all types instead of the code for a manifest.
+    #[argh(switch)]
+    pub emit_types_crate: bool,
+
+    #[argh(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Scaffold(ScaffoldArgs),
+    Lint(LintArgs),
+    Graph(GraphArgs),
+    Docs(DocsArgs),
+    Asyncapi(AsyncapiArgs),
+    Fuzz(FuzzArgs),
+}
+
+#[derive(FromArgs)]
+/// Check --manifest or --interface and every interface and types file they
+/// reference against the schemas, or all files of the search roots if
+/// neither is given.
+#[argh(subcommand, name = "lint")]
+struct LintArgs {
+    /// check this EVerest config and how it wires up the modules instead.
+    #[argh(option)]
+    pub config: Option<PathBuf>,
+}
+
+#[derive(FromArgs)]
+/// Create a new module with a manifest, Cargo.toml, build.rs and a main.rs
+/// with stubs for all callbacks. Uses the search roots and switches given
+/// before the subcommand.
+#[argh(subcommand, name = "scaffold")]
+struct ScaffoldArgs {
+    /// name of the module, e.g. `RsFooBar`.
+    #[argh(positional)]
+    pub name: String,
+
+    /// a provided implementation, given as `<implementation_id>=<interface>`.
+    /// At least one is required, can be repeated.
+    #[argh(option)]
+    pub provides: Vec<String>,
+
+    /// a requirement, given as `<requirement_id>=<interface>`. Can be
+    /// repeated.
+    #[argh(option)]
+    pub requires: Vec<String>,
+
+    /// an author of the module for the manifest. Can be repeated.
+    #[argh(option)]
+    pub author: Vec<String>,
+
+    /// directory of the new module. Defaults to the module name.
+    #[argh(option)]
+    pub out_dir: Option<PathBuf>,
+}
+
+#[derive(FromArgs)]
+/// Render how an EVerest config wires up its modules as Graphviz DOT or
+/// Mermaid. Unresolved and over-subscribed requirements are highlighted.
+#[argh(subcommand, name = "graph")]
+struct GraphArgs {
+    /// the EVerest config to render.
+    #[argh(option)]
+    pub config: PathBuf,
+
+    /// format of the graph, `dot` (the default) or `mermaid`.
+    #[argh(option, default = "GraphFormat::Dot")]
+    pub format: GraphFormat,
+
+    /// file to write the graph to instead of stdout.
+    #[argh(option)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(FromArgs)]
+/// Render every interface, types file and module manifest of the search roots
+/// and --manifest, if given, as Markdown or HTML pages with an index.
+#[argh(subcommand, name = "docs")]
+struct DocsArgs {
+    /// directory to write the pages to.
+    #[argh(option)]
     pub out_dir: PathBuf,
+
+    /// format of the pages, `markdown` (the default) or `html`.
+    #[argh(option, default = "DocsFormat::Markdown")]
+    pub format: DocsFormat,
+}
+
+#[derive(FromArgs)]
+/// Export what --manifest provides as AsyncAPI document, with a JSON Schema
+/// file for every type it uses.
+#[argh(subcommand, name = "asyncapi")]
+struct AsyncapiArgs {
+    /// directory to write `asyncapi.json` and the `schemas` to.
+    #[argh(option)]
+    pub out_dir: PathBuf,
+}
+
+#[derive(FromArgs)]
+/// Write a cargo-fuzz crate with one target per dispatcher of --manifest,
+/// which feeds arbitrary JSON into it. Uses the search roots and switches
+/// given before the subcommand.
+#[argh(subcommand, name = "fuzz")]
+struct FuzzArgs {
+    /// directory of the fuzz crate, usually `fuzz` next to the manifest.
+    #[argh(option)]
+    pub out_dir: PathBuf,
+}
+
+/// Splits the `<id>=<interface>` arguments of `scaffold`.
+fn slots(entries: &[String]) -> Result<Vec<(&str, &str)>> {
+    entries
+        .iter()
+        .map(|entry| {
+            entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Expected <id>=<interface>, got `{entry}`"))
+        })
+        .collect()
 }
 
 pub fn main() -> Result<()> {
     let args: Args = argh::from_env();
 
-    Builder::new(args.manifest, args.everest_core)
-        .out_dir(args.out_dir)
-        .generate()?;
+    let mut builder = match (args.manifest, args.interface.is_empty()) {
+        (Some(manifest), true) => Builder::new(manifest, args.everest_core),
+        (None, false) => args.everest_core.into_iter().fold(
+            Builder::for_interfaces(&args.interface),
+            Builder::search_root,
+        ),
+        (None, true) if args.emit_types_crate || args.command.is_some() => args
+            .everest_core
+            .into_iter()
+            .fold(Builder::default(), Builder::search_root),
+        _ => {
+            return Err(anyhow!(
+                "Either --manifest, --interface or --emit-types-crate is required"
+            ))
+        }
+    };
+    if let Some(prefix) = args.prefix {
+        builder = builder.prefix(prefix);
+    }
+    if let Some(dir) = args.template_dir {
+        builder = builder.template_dir(dir);
+    }
+    for root in args.namespaced_root {
+        let (namespace, path) = root
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected <namespace>=<path>, got `{root}`"))?;
+        builder = builder.namespaced_root(namespace, path);
+    }
+
+    if let Some(name) = args.types_crate {
+        builder = builder.types_crate(name);
+    }
+    if let Some(visibility) = args.visibility {
+        builder = builder.visibility(visibility);
+    }
+    if let Some(path) = args.root_module {
+        builder = builder.root_module(path);
+    }
+
+    let builder = builder
+        .command_args_structs(args.command_args_structs)
+        .precise_integers(args.precise_integers)
+        .split_modules(args.split_modules)
+        .json_schema(args.json_schema)
+        .arbitrary(args.arbitrary);
+    match args.command {
+        Some(Command::Scaffold(scaffold)) => {
+            let out_dir = scaffold
+                .out_dir
+                .unwrap_or_else(|| PathBuf::from(&scaffold.name));
+            return builder.out_dir(out_dir).scaffold(
+                &scaffold.name,
+                &slots(&scaffold.provides)?,
+                &slots(&scaffold.requires)?,
+                &scaffold.author,
+            );
+        }
+        Some(Command::Lint(lint)) => {
+            let diagnostics = match lint.config {
+                Some(config) => builder.lint_config(config),
+                None => builder.lint()?,
+            };
+            for diagnostic in &diagnostics {
+                eprintln!("{diagnostic}");
+            }
+            if !diagnostics.is_empty() {
+                bail!("Found {} problems", diagnostics.len());
+            }
+            return Ok(());
+        }
+        Some(Command::Graph(graph)) => {
+            let out = builder.graph(&graph.config, graph.format)?;
+            for warning in &out.warnings {
+                eprintln!("warning: {warning}");
+            }
+            let code = out.files.values().next().unwrap();
+            match graph.output {
+                Some(path) => std::fs::write(&path, code)
+                    .with_context(|| format!("Could not write {}", path.display()))?,
+                None => print!("{code}"),
+            }
+            return Ok(());
+        }
+        Some(Command::Docs(docs)) => {
+            return builder.out_dir(docs.out_dir).generate_docs(docs.format);
+        }
+        Some(Command::Asyncapi(asyncapi)) => {
+            return builder.out_dir(asyncapi.out_dir).generate_asyncapi();
+        }
+        Some(Command::Fuzz(fuzz)) => {
+            return builder.out_dir(fuzz.out_dir).generate_fuzz_targets();
+        }
+        None => {}
+    }
+
+    let out_dir = args
+        .out_dir
+        .ok_or_else(|| anyhow!("--out-dir is required"))?;
+    let builder = builder.out_dir(out_dir);
+    if args.emit_types_crate {
+        builder.generate_types_crate()?;
+    } else {
+        builder.generate()?;
+    }
 
     Ok(())
 }
@@ -1,6 +1,9 @@
 use crate::schema::{
-    manifest::{ConfigEntry, ConfigEnum},
-    types::{DataTypes, ObjectOptions, StringOptions, Type, TypeBase, TypeEnum},
+    manifest::{ConfigEntry, ConfigEnum, PropertyValue},
+    types::{
+        ArrayOptions, DataTypes, IntegerOptions, ObjectOptions, StringOptions, Type, TypeBase,
+        TypeEnum,
+    },
     Interface, Manifest,
 };
 use anyhow::{anyhow, bail, Context, Result};
@@ -9,21 +12,193 @@ use minijinja::{Environment, UndefinedBehavior};
 use serde::{de::DeserializeOwned, Serialize};
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 
 // We include the JINJA templates into the binary. This has the disadvantage
 // that every change to the templates requires a recompilation, but the
 // advantage that the codegen library/binary is truly standalone and needs
 // nothing shipped with it to work.
+const ARGS_JINJA: &str = include_str!("../jinja/args.jinja2");
 const CLIENT_JINJA: &str = include_str!("../jinja/client.jinja2");
 const CONFIG_JINJA: &str = include_str!("../jinja/config.jinja2");
+const DOCS_HTML_JINJA: &str = include_str!("../jinja/docs_html.jinja2");
+const DOCS_MARKDOWN_JINJA: &str = include_str!("../jinja/docs_markdown.jinja2");
+const FUZZ_CARGO_JINJA: &str = include_str!("../jinja/fuzz_cargo.jinja2");
+const FUZZ_TARGET_JINJA: &str = include_str!("../jinja/fuzz_target.jinja2");
+const GENERATED_JINJA: &str = include_str!("../jinja/generated.jinja2");
+const GRAPH_DOT_JINJA: &str = include_str!("../jinja/graph_dot.jinja2");
+const GRAPH_MERMAID_JINJA: &str = include_str!("../jinja/graph_mermaid.jinja2");
+const INTERFACE_JINJA: &str = include_str!("../jinja/interface.jinja2");
 const MODULE_JINJA: &str = include_str!("../jinja/module.jinja2");
+const SCAFFOLD_BUILD_JINJA: &str = include_str!("../jinja/scaffold_build.jinja2");
+const SCAFFOLD_CARGO_JINJA: &str = include_str!("../jinja/scaffold_cargo.jinja2");
+const SCAFFOLD_MAIN_JINJA: &str = include_str!("../jinja/scaffold_main.jinja2");
+const SCAFFOLD_MANIFEST_JINJA: &str = include_str!("../jinja/scaffold_manifest.jinja2");
 const SERVICE_JINJA: &str = include_str!("../jinja/service.jinja2");
 const TYPES_JINJA: &str = include_str!("../jinja/types.jinja2");
+const TYPES_CRATE_JINJA: &str = include_str!("../jinja/types_crate.jinja2");
 
+/// Opt-in switches which change the shape of the generated code.
+#[derive(Debug, Clone, Serialize)]
+pub struct Options {
+    /// Pass command arguments as one generated `<Interface><Command>Args`
+    /// struct instead of as positional parameters.
+    pub command_args_structs: bool,
+    /// Pick the narrowest integer type which holds the schema bounds instead
+    /// of always using `i64`.
+    pub precise_integers: bool,
+    /// Write a module tree with one file per types module and interface
+    /// instead of a single `generated.rs`.
+    pub split_modules: bool,
+    /// Derive `schemars::JsonSchema` for the types and argument structs.
+    pub json_schema: bool,
+    /// Implement `proptest::arbitrary::Arbitrary` for the types and argument
+    /// structs.
+    pub arbitrary: bool,
+    /// A directory with `<name>.jinja2` files which replace the built-in
+    /// template `<name>` or add new ones to include.
+    #[serde(skip)]
+    pub template_dir: Option<PathBuf>,
+    /// The name of the crate generated by [emit_types_crate] to take the
+    /// types from instead of generating them into the module.
+    pub types_crate: Option<String>,
+    /// The visibility of the generated items. Must not be relative to the
+    /// module the item is in, i.e. `pub(super)` does not work.
+    pub visibility: String,
+    /// The absolute path of the `generated` module itself, i.e.
+    /// `crate::<path of the module generated.rs is included into>::generated`.
+    pub root_module: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            command_args_structs: false,
+            precise_integers: false,
+            split_modules: false,
+            json_schema: false,
+            arbitrary: false,
+            template_dir: None,
+            types_crate: None,
+            visibility: "pub(crate)".to_string(),
+            root_module: "crate::generated".to_string(),
+        }
+    }
+}
+
+impl Options {
+    /// Returns the path of the module which contains the types.
+    fn types_root(&self) -> String {
+        match &self.types_crate {
+            Some(name) => format!("::{name}"),
+            None => format!("{}::types", self.root_module),
+        }
+    }
+
+    /// Fails for options which would lead to code that does not compile.
+    fn validate(&self) -> Result<()> {
+        let v = self.visibility.as_str();
+        if !(v == "pub" || v == "pub(crate)" || v.starts_with("pub(in crate::")) {
+            bail!("Unsupported visibility `{v}`, use `pub`, `pub(crate)` or `pub(in crate::...)`");
+        }
+        let path = self.root_module.as_str();
+        let segments = path.split("::").collect::<Vec<_>>();
+        let is_ident =
+            |s: &&str| is_identifier(s) && !matches!(*s, "crate" | "self" | "super" | "Self");
+        if segments.len() < 2
+            || segments[0] != "crate"
+            || segments.last() != Some(&"generated")
+            || !segments[1..].iter().all(is_ident)
+        {
+            bail!("Unsupported root module `{path}`, use `crate::generated` or `crate::<module>::generated`");
+        }
+        Ok(())
+    }
+}
+
+/// What to generate the code for.
+#[derive(Debug, Clone, Default)]
+pub enum Source {
+    /// Neither a manifest nor interfaces, e.g. for the types crate or when
+    /// linting everything in the search roots.
+    #[default]
+    None,
+    /// A module described by the manifest at the path.
+    Manifest(PathBuf),
+    /// Only the traits and types of the named interfaces, without a module.
+    Interfaces(Vec<String>),
+}
+
+/// Reads and parses the YAML file at `path`. Parse errors point to the
+/// offending line and column.
+pub(crate) fn parse_yaml<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let blob =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_yaml::from_str(&blob).map_err(|err| {
+        let location = match err.location() {
+            Some(l) => format!("{}:{}:{}", path.display(), l.line(), l.column()),
+            None => path.display().to_string(),
+        };
+        anyhow::Error::new(err).context(format!("Failed to parse {location}"))
+    })
+}
+
+/// Returns the name of the module whose manifest is at `manifest_path`, i.e.
+/// the name of the directory it is in.
+pub(crate) fn module_name(manifest_path: &Path) -> Result<String> {
+    manifest_path
+        .canonicalize()
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?
+        .parent()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().into_owned())
+        .context("Cannot derive the module name from the manifest path")
+}
+
+/// A directory which contains `interfaces/` and `types/` subdirectories.
+#[derive(Debug, Clone)]
+pub struct SearchRoot {
+    pub path: PathBuf,
+    /// If set, the root only provides names inside this namespace, i.e. the
+    /// name `<namespace>/foo` is looked up as `foo` in this root.
+    pub namespace: Option<String>,
+}
+
+impl SearchRoot {
+    /// Returns the path under which `name` would be defined in this root.
+    fn resolve(&self, prefix: &str, name: &str) -> Option<PathBuf> {
+        let name = match &self.namespace {
+            None => name,
+            Some(namespace) => name.strip_prefix(namespace)?.strip_prefix('/')?,
+        };
+        Some(self.path.join(format!("{prefix}/{name}.yaml")))
+    }
+}
+
+impl std::fmt::Display for SearchRoot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.namespace {
+            None => write!(f, "{}", self.path.display()),
+            Some(namespace) => write!(f, "{namespace}={}", self.path.display()),
+        }
+    }
+}
+
+fn display_roots(roots: &[SearchRoot]) -> String {
+    roots
+        .iter()
+        .map(|r| r.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Loads `<prefix>/<name>.yaml` from the first root which defines it. Later
+/// roots defining the same name are shadowed, which we report in `warnings`.
 fn lazy_load<'a, T: DeserializeOwned>(
     storage: &'a mut HashMap<String, T>,
-    everest_core: &Vec<PathBuf>,
+    files: &mut BTreeSet<PathBuf>,
+    warnings: &mut Vec<String>,
+    everest_core: &[SearchRoot],
     prefix: &str,
     postfix: &str,
 ) -> Result<&'a T> {
@@ -33,46 +208,40 @@ fn lazy_load<'a, T: DeserializeOwned>(
 
     let mut matches = everest_core
         .iter()
-        .filter_map(|core| {
-            let p = core.join(format!("{prefix}/{postfix}.yaml"));
-            // If the file is missing we ignore the error since it may be
-            // present in an different root.
-            let Ok(blob) = fs::read_to_string(&p) else {
-                return None;
-            };
-            let out = serde_yaml::from_str(&blob).with_context(|| format!("Failed to parse {p:?}"));
-            match out {
-                Err(err) => {
-                    println!("{err:?}");
-                    None
-                }
-                Ok(res) => Some(res),
-            }
-        })
-        .collect::<Vec<_>>();
+        .filter_map(|root| root.resolve(prefix, postfix))
+        .filter(|p| p.is_file());
 
-    assert!(
-        matches.len() == 1,
-        "The name `{prefix}/{postfix}` must be defined exactly once: Found {}",
-        { matches.len() }
-    );
+    let Some(path) = matches.next() else {
+        bail!(
+            "Could not find `{prefix}/{postfix}.yaml` in any of the search roots: [{}]",
+            display_roots(everest_core)
+        );
+    };
+    for shadowed in matches {
+        warnings.push(format!("{} shadows {}", path.display(), shadowed.display()));
+    }
 
-    storage.insert(postfix.to_string(), matches.pop().unwrap());
+    files.insert(path.clone());
+    storage.insert(postfix.to_string(), parse_yaml(&path)?);
     Ok(storage.get(postfix).unwrap())
 }
 
 /// A lazy loader for YAML files. If the same file is requested twice, it will
 /// not be re-parsed again.
 #[derive(Default, Debug)]
-struct YamlRepo {
-    // This might be also a HashMap of "namespaces" and paths.
-    everest_core: Vec<PathBuf>,
+pub(crate) struct YamlRepo {
+    /// The roots to search in, in order of precedence.
+    everest_core: Vec<SearchRoot>,
     interfaces: HashMap<String, Interface>,
     data_types: HashMap<String, DataTypes>,
+    manifests: HashMap<String, Manifest>,
+    /// Every file we have read so far.
+    files: BTreeSet<PathBuf>,
+    warnings: Vec<String>,
 }
 
 impl YamlRepo {
-    pub fn new(everest_core: Vec<PathBuf>) -> Self {
+    pub fn new(everest_core: Vec<SearchRoot>) -> Self {
         Self {
             everest_core,
             ..Default::default()
@@ -80,44 +249,146 @@ impl YamlRepo {
     }
 
     pub fn get_interface<'a>(&'a mut self, name: &str) -> Result<&'a Interface> {
-        lazy_load(&mut self.interfaces, &self.everest_core, "interfaces", name)
+        lazy_load(
+            &mut self.interfaces,
+            &mut self.files,
+            &mut self.warnings,
+            &self.everest_core,
+            "interfaces",
+            name,
+        )
     }
 
     pub fn get_data_types<'a>(&'a mut self, name: &str) -> Result<&'a DataTypes> {
-        lazy_load(&mut self.data_types, &self.everest_core, "types", name)
+        lazy_load(
+            &mut self.data_types,
+            &mut self.files,
+            &mut self.warnings,
+            &self.everest_core,
+            "types",
+            name,
+        )
+    }
+
+    /// Returns the manifest of the module `name`, which is read from
+    /// `modules/<name>/manifest.yaml` in the search roots.
+    pub fn get_manifest<'a>(&'a mut self, name: &str) -> Result<&'a Manifest> {
+        lazy_load(
+            &mut self.manifests,
+            &mut self.files,
+            &mut self.warnings,
+            &self.everest_core,
+            "modules",
+            &format!("{name}/manifest"),
+        )
+    }
+
+    /// Returns the path `<prefix>/<name>.yaml` is read from, if it exists.
+    pub fn find(&self, prefix: &str, name: &str) -> Option<PathBuf> {
+        self.everest_core
+            .iter()
+            .filter_map(|root| root.resolve(prefix, name))
+            .find(|p| p.is_file())
+    }
+
+    /// Returns the names of all types files in the search roots, e.g.
+    /// `powermeter` or `<namespace>/powermeter`.
+    pub fn data_types_names(&self) -> Result<BTreeSet<String>> {
+        self.file_names("types")
+    }
+
+    /// Returns the names of all files in the `prefix` directory of the search
+    /// roots, see [YamlRepo::data_types_names].
+    pub fn file_names(&self, prefix: &str) -> Result<BTreeSet<String>> {
+        let mut names = BTreeSet::new();
+        for root in &self.everest_core {
+            let types_dir = root.path.join(prefix);
+            let mut dirs = vec![types_dir.clone()];
+            while let Some(dir) = dirs.pop() {
+                if !dir.is_dir() {
+                    continue;
+                }
+                let entries = fs::read_dir(&dir)
+                    .with_context(|| format!("Failed to read {}", dir.display()))?;
+                for entry in entries {
+                    let path = entry?.path();
+                    if path.is_dir() {
+                        dirs.push(path);
+                        continue;
+                    }
+                    if path.extension().and_then(|e| e.to_str()) != Some("yaml") {
+                        continue;
+                    }
+                    let name = path
+                        .strip_prefix(&types_dir)?
+                        .with_extension("")
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+                        .collect::<Vec<_>>()
+                        .join("/");
+                    names.insert(match &root.namespace {
+                        Some(namespace) => format!("{namespace}/{name}"),
+                        None => name,
+                    });
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    /// Returns all files which were read so far.
+    pub fn files(&self) -> &BTreeSet<PathBuf> {
+        &self.files
+    }
+
+    /// Returns the problems found so far which did not prevent loading.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
     }
 }
 
-// We just pull out of ObjectOptions what we really need for codegen.
+/// A named type, i.e. the target of a `$ref`. We just pull out of
+/// ObjectOptions what we really need for codegen.
 #[derive(Clone, Hash, PartialOrd, Ord, PartialEq, Eq)]
-struct TypeRef {
+pub struct TypeRef {
     /// The same as the file name under everest-core/types.
-    module_path: Vec<String>,
-    type_name: String,
+    pub module_path: Vec<String>,
+    pub type_name: String,
 }
 
 impl TypeRef {
-    fn from_object(args: &ObjectOptions) -> Result<Self> {
-        assert!(args.object_reference.is_some());
-        assert!(
-            args.properties.is_empty(),
-            "Found an object with $ref, but also with properties. Cannot handle that case."
-        );
-        Self::from_reference(args.object_reference.as_ref().unwrap())
+    fn from_object(args: &ObjectOptions, scope: Option<&[String]>) -> Result<Self> {
+        let Some(r) = &args.object_reference else {
+            bail!("Expected an object with $ref");
+        };
+        if !args.properties.is_empty() {
+            bail!(
+                "Found an object with $ref {r}, but also with properties. Cannot handle that case."
+            );
+        }
+        Self::from_reference(r, scope)
     }
 
-    fn from_string(args: &StringOptions) -> Result<Self> {
-        assert!(args.object_reference.is_some());
-        Self::from_reference(args.object_reference.as_ref().unwrap())
+    fn from_string(args: &StringOptions, scope: Option<&[String]>) -> Result<Self> {
+        let Some(r) = &args.object_reference else {
+            bail!("Expected a string with $ref");
+        };
+        Self::from_reference(r, scope)
     }
 
-    fn from_reference(r: &str) -> Result<Self> {
+    /// Parses references of the form `/<module>#/<Type>`. Local references
+    /// (`#/<Type>`) are resolved against `scope`, which is the module path of
+    /// the types file the reference appears in.
+    pub(crate) fn from_reference(r: &str, scope: Option<&[String]>) -> Result<Self> {
         let parts: Vec<_> = r.trim_start_matches('/').split("#/").collect();
         if parts.len() != 2 {
             bail!("Unexpected type reference: {}", r);
         }
-        let module_name = parts[0].to_string();
-        let module_path = module_name.split('/').map(|s| s.to_string()).collect();
+        let module_path = match (parts[0], scope) {
+            ("", Some(scope)) => scope.to_vec(),
+            ("", None) => bail!("Local type reference {r} used outside of a types file"),
+            (module_name, _) => module_name.split('/').map(|s| s.to_string()).collect(),
+        };
         let type_name = parts[1].to_string();
         Ok(Self {
             module_path,
@@ -125,12 +396,12 @@ impl TypeRef {
         })
     }
 
-    pub fn module_name(&self) -> String {
-        format!("crate::generated::types::{}", self.module_path.join("::"),)
+    pub(crate) fn module_name(&self, options: &Options) -> String {
+        format!("{}::{}", options.types_root(), self.module_path.join("::"))
     }
 
-    pub fn absolute_type_path(&self) -> String {
-        format!("{}::{}", self.module_name(), self.type_name)
+    pub(crate) fn absolute_type_path(&self, options: &Options) -> String {
+        format!("{}::{}", self.module_name(options), self.type_name)
     }
 }
 
@@ -145,7 +416,319 @@ impl std::fmt::Debug for TypeRef {
     }
 }
 
-fn as_typename(arg: &TypeBase, type_refs: &mut BTreeSet<TypeRef>) -> Result<String> {
+/// Returns the type `arg` refers to, if `arg` is a plain `$ref`. Such a
+/// reference is embedded by value into the parent, as opposed to for example
+/// references behind an array.
+fn direct_type_ref(arg: &TypeBase, scope: Option<&[String]>) -> Result<Option<TypeRef>> {
+    use TypeBase::*;
+    use TypeEnum::*;
+    Ok(match arg {
+        Single(String(args)) if args.object_reference.is_some() => {
+            Some(TypeRef::from_string(args, scope)?)
+        }
+        Single(Object(args)) if args.object_reference.is_some() => {
+            Some(TypeRef::from_object(args, scope)?)
+        }
+        _ => None,
+    })
+}
+
+/// Returns the schema `default` of `arg` as a Rust expression which evaluates
+/// to the type returned by [as_typename].
+fn default_value(
+    arg: &TypeBase,
+    scope: Option<&[String]>,
+    options: &Options,
+) -> Result<Option<String>> {
+    use TypeBase::*;
+    use TypeEnum::*;
+    Ok(match arg {
+        Single(Boolean(args)) => args.default.map(|v| v.to_string()),
+        Single(Integer(args)) => args.default.map(|v| v.to_string()),
+        // The debug representation always contains a decimal point or an
+        // exponent, so it is a valid float literal.
+        Single(Number(args)) => args.default.map(|v| format!("{v:?}")),
+        Single(String(args)) => match &args.default {
+            None => None,
+            Some(v) if args.object_reference.is_some() => Some(format!(
+                "{}::{v}",
+                TypeRef::from_string(args, scope)?.absolute_type_path(options)
+            )),
+            Some(v) => Some(format!("{v:?}.to_string()")),
+        },
+        _ => None,
+    })
+}
+
+/// The integer types we pick from, together with their value range. The
+/// unsigned type comes first if both cover the same bounds.
+const INTEGER_TYPES: [(&str, i128, i128); 8] = [
+    ("u8", u8::MIN as i128, u8::MAX as i128),
+    ("i8", i8::MIN as i128, i8::MAX as i128),
+    ("u16", u16::MIN as i128, u16::MAX as i128),
+    ("i16", i16::MIN as i128, i16::MAX as i128),
+    ("u32", u32::MIN as i128, u32::MAX as i128),
+    ("i32", i32::MIN as i128, i32::MAX as i128),
+    ("u64", u64::MIN as i128, u64::MAX as i128),
+    ("i64", i64::MIN as i128, i64::MAX as i128),
+];
+
+/// Returns the integer type for `args` together with its value range.
+fn integer_type(args: &IntegerOptions, options: &Options) -> (&'static str, i128, i128) {
+    let i64_type = INTEGER_TYPES[INTEGER_TYPES.len() - 1];
+    if !options.precise_integers {
+        return i64_type;
+    }
+    let minimum = i128::from(args.minimum.unwrap_or(i64::MIN));
+    let maximum = i128::from(args.maximum.unwrap_or(i64::MAX));
+    INTEGER_TYPES
+        .into_iter()
+        .find(|(_, min, max)| *min <= minimum && maximum <= *max)
+        .unwrap_or(i64_type)
+}
+
+/// Returns the function which deserializes `arg` if its schema bounds are
+/// narrower than the range of the type returned by [as_typename]. For arrays
+/// of integers, the bounds are checked for every item.
+fn bounded_deserializer(arg: &TypeBase, options: &Options) -> Option<String> {
+    let args = match arg {
+        TypeBase::Single(TypeEnum::Integer(args)) => args,
+        TypeBase::Single(TypeEnum::Array(ArrayOptions {
+            items: Some(items), ..
+        })) => return bounded_deserializer(&items.arg, options),
+        _ => return None,
+    };
+    if !options.precise_integers {
+        return None;
+    }
+    let (_, type_min, type_max) = integer_type(args, options);
+    let minimum = args.minimum.map_or(type_min, i128::from);
+    let maximum = args.maximum.map_or(type_max, i128::from);
+    if minimum == type_min && maximum == type_max {
+        return None;
+    }
+    // Negative const generic arguments must be wrapped in braces.
+    let literal = |v: i128| {
+        if v < 0 {
+            format!("{{{v}}}")
+        } else {
+            v.to_string()
+        }
+    };
+    Some(format!(
+        "::everestrs::deserialize_bounded::<_, _, {}, {}>",
+        literal(minimum),
+        literal(maximum)
+    ))
+}
+
+/// Returns the arguments for `#[schemars(...)]` which carry the constraints
+/// of `arg` over into the derived schema. `optional` fields can also be
+/// `null`.
+fn schemars_annotations(arg: &TypeBase, optional: bool) -> Vec<String> {
+    let TypeBase::Single(t) = arg else {
+        return Vec::new();
+    };
+    let mut annotations = validation_annotations(t);
+    if let TypeEnum::String(args) = t {
+        if args.format.is_some() {
+            annotations.push("extend(\"format\" = \"date-time\")".to_string());
+        }
+        // Named enums derive their own schema.
+        if let (Some(items), None) = (&args.enum_items, &args.object_reference) {
+            let mut items: Vec<_> = items.iter().map(|item| format!("{item:?}")).collect();
+            if optional {
+                items.push("null".to_string());
+            }
+            annotations.push(format!("extend(\"enum\" = [{}])", items.join(", ")));
+        }
+    }
+    annotations
+}
+
+/// Returns the `range`, `length` and `regex` schemars attributes of `t`,
+/// which can also be applied to the items of an array with `inner`.
+fn validation_annotations(t: &TypeEnum) -> Vec<String> {
+    fn bounds(name: &str, min: Option<String>, max: Option<String>) -> Option<String> {
+        let bounds: Vec<_> = [("min", min), ("max", max)]
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|v| format!("{key} = {v}")))
+            .collect();
+        (!bounds.is_empty()).then(|| format!("{name}({})", bounds.join(", ")))
+    }
+    let mut annotations = Vec::new();
+    match t {
+        TypeEnum::Integer(args) => annotations.extend(bounds(
+            "range",
+            args.minimum.map(|v| v.to_string()),
+            args.maximum.map(|v| v.to_string()),
+        )),
+        // The debug representation is always a float literal.
+        TypeEnum::Number(args) => annotations.extend(bounds(
+            "range",
+            args.minimum.map(|v| format!("{v:?}")),
+            args.maximum.map(|v| format!("{v:?}")),
+        )),
+        TypeEnum::String(args) if args.object_reference.is_none() => {
+            annotations.extend(bounds(
+                "length",
+                args.min_length.map(|v| v.to_string()),
+                args.max_length.map(|v| v.to_string()),
+            ));
+            if let Some(pattern) = &args.pattern {
+                annotations.push(format!("regex(pattern = {pattern:?})"));
+            }
+        }
+        TypeEnum::Array(args) => {
+            annotations.extend(bounds(
+                "length",
+                args.min_items.map(|v| v.to_string()),
+                args.max_items.map(|v| v.to_string()),
+            ));
+            if let Some(TypeBase::Single(items)) = args.items.as_ref().map(|items| &items.arg) {
+                let inner = validation_annotations(items);
+                if !inner.is_empty() {
+                    annotations.push(format!("inner({})", inner.join(", ")));
+                }
+            }
+        }
+        _ => {}
+    }
+    annotations
+}
+
+/// How deep the `Arbitrary` impls nest named types. Deeper down, optional
+/// properties are `None` and arrays without `minItems` are empty, so that
+/// recursive types terminate.
+const ARBITRARY_MAX_DEPTH: u32 = 4;
+
+/// How many items the `Arbitrary` impls generate at most beyond `minItems`
+/// for arrays without `maxItems`.
+const ARBITRARY_EXTRA_ITEMS: usize = 3;
+
+/// The strings the `Arbitrary` impls generate for `format: date-time`.
+const ARBITRARY_DATE_TIME: &str =
+    r"[0-9]{4}-(0[1-9]|1[0-2])-(0[1-9]|1[0-9]|2[0-8])T([01][0-9]|2[0-3]):[0-5][0-9]:[0-5][0-9]Z";
+
+/// Returns `pattern` without the `^` and `$` anchors, which proptest does
+/// not support. It generates strings matching the whole pattern anyway.
+fn unanchored(pattern: &str) -> &str {
+    let pattern = pattern.strip_prefix('^').unwrap_or(pattern);
+    match pattern.strip_suffix('$') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => pattern,
+    }
+}
+
+/// Wraps the `strategy` of an optional property.
+fn optional_strategy(strategy: &str) -> String {
+    format!(
+        "if depth < {ARBITRARY_MAX_DEPTH} {{ proptest::option::of({strategy}).boxed() }} \
+         else {{ Just(None).boxed() }}"
+    )
+}
+
+/// Returns the expression of a proptest strategy for the type returned by
+/// [as_typename], which respects the bounds, lengths, patterns and `enum`s
+/// of the schema. It is evaluated in `arbitrary_with(depth)` with
+/// `proptest::prelude::*` in scope. Named types are generated with their own
+/// `Arbitrary` impl one level deeper. Untyped values are always empty.
+fn arbitrary_strategy(
+    arg: &TypeBase,
+    scope: Option<&[String]>,
+    options: &Options,
+) -> Result<String> {
+    use TypeBase::*;
+    use TypeEnum::*;
+    let named = |t: TypeRef| format!("any_with::<{}>(depth + 1)", t.absolute_type_path(options));
+    Ok(match arg {
+        Single(Null) => "Just(())".to_string(),
+        Single(Boolean(_)) => "any::<bool>()".to_string(),
+        Single(String(args)) => {
+            if args.object_reference.is_some() {
+                named(TypeRef::from_string(args, scope)?)
+            } else if let Some(items) = &args.enum_items {
+                format!("proptest::sample::select(vec!{items:?}).prop_map(String::from)")
+            } else if args.format.is_some() {
+                format!("proptest::string::string_regex({ARBITRARY_DATE_TIME:?}).unwrap()")
+            } else if let Some(pattern) = &args.pattern {
+                let mut strategy = format!(
+                    "proptest::string::string_regex({:?}).expect({:?})",
+                    unanchored(pattern),
+                    format!("Unsupported pattern {pattern}")
+                );
+                if args.min_length.is_some() || args.max_length.is_some() {
+                    strategy.push_str(&format!(
+                        ".prop_filter(\"length\", |s| ({}..={}).contains(&s.chars().count()))",
+                        args.min_length.unwrap_or(0),
+                        args.max_length.unwrap_or(usize::MAX)
+                    ));
+                }
+                strategy
+            } else if args.min_length.is_some() || args.max_length.is_some() {
+                let min = args.min_length.unwrap_or(0);
+                let max = args.max_length.unwrap_or(min + ARBITRARY_EXTRA_ITEMS);
+                format!("proptest::string::string_regex(\"\\\\PC{{{min},{max}}}\").unwrap()")
+            } else {
+                "any::<String>()".to_string()
+            }
+        }
+        Single(Number(args)) => match (args.minimum, args.maximum) {
+            (None, None) => {
+                // NaN and the infinities do not survive JSON.
+                "proptest::num::f64::NORMAL | proptest::num::f64::ZERO".to_string()
+            }
+            (minimum, maximum) => format!(
+                "{:?}..={:?}",
+                minimum.unwrap_or(f64::MIN),
+                maximum.unwrap_or(f64::MAX)
+            ),
+        },
+        Single(Integer(args)) => {
+            let (name, _, _) = integer_type(args, options);
+            match (args.minimum, args.maximum) {
+                (None, None) => format!("any::<{name}>()"),
+                (minimum, maximum) => format!(
+                    "{}..={}",
+                    minimum.map_or(format!("{name}::MIN"), |v| format!("{v}{name}")),
+                    maximum.map_or(format!("{name}::MAX"), |v| format!("{v}{name}"))
+                ),
+            }
+        }
+        Single(Object(args)) => {
+            if args.object_reference.is_some() {
+                named(TypeRef::from_object(args, scope)?)
+            } else {
+                "Just(::serde_json::Value::Object(Default::default()))".to_string()
+            }
+        }
+        Single(Array(args)) => {
+            let item = match &args.items {
+                Some(items) => arbitrary_strategy(&items.arg, scope, options)?,
+                None => "Just(::serde_json::Value::Null)".to_string(),
+            };
+            let min = args.min_items.unwrap_or(0);
+            let max = args.max_items.unwrap_or(min + ARBITRARY_EXTRA_ITEMS);
+            let strategy = format!("proptest::collection::vec({item}, {min}..={max})");
+            if min == 0 {
+                format!(
+                    "if depth < {ARBITRARY_MAX_DEPTH} {{ {strategy}.boxed() }} \
+                     else {{ Just(Vec::new()).boxed() }}"
+                )
+            } else {
+                strategy
+            }
+        }
+        Multiple(_) => "Just(::serde_json::Value::Null)".to_string(),
+    })
+}
+
+fn as_typename(
+    arg: &TypeBase,
+    scope: Option<&[String]>,
+    options: &Options,
+    type_refs: &mut BTreeSet<TypeRef>,
+) -> Result<String> {
     use TypeBase::*;
     use TypeEnum::*;
     Ok(match arg {
@@ -155,20 +738,20 @@ fn as_typename(arg: &TypeBase, type_refs: &mut BTreeSet<TypeRef>) -> Result<Stri
             if args.object_reference.is_none() {
                 "String".to_string()
             } else {
-                let t = TypeRef::from_string(args)?;
-                let name = t.absolute_type_path();
+                let t = TypeRef::from_string(args, scope)?;
+                let name = t.absolute_type_path(options);
                 type_refs.insert(t);
                 name
             }
         }
         Single(Number(_)) => "f64".to_string(),
-        Single(Integer(_)) => "i64".to_string(),
+        Single(Integer(args)) => integer_type(args, options).0.to_string(),
         Single(Object(args)) => {
             if args.object_reference.is_none() {
                 "::serde_json::Value".to_string()
             } else {
-                let t = TypeRef::from_object(args)?;
-                let name = t.absolute_type_path();
+                let t = TypeRef::from_object(args, scope)?;
+                let name = t.absolute_type_path(options);
                 type_refs.insert(t);
                 name
             }
@@ -176,7 +759,7 @@ fn as_typename(arg: &TypeBase, type_refs: &mut BTreeSet<TypeRef>) -> Result<Stri
         Single(Array(args)) => match args.items {
             None => "Vec<::serde_json::Value>".to_string(),
             Some(ref v) => {
-                let item_type = as_typename(&v.arg, type_refs)?;
+                let item_type = as_typename(&v.arg, scope, options, type_refs)?;
                 format!("Vec<{item_type}>")
             }
         },
@@ -184,64 +767,127 @@ fn as_typename(arg: &TypeBase, type_refs: &mut BTreeSet<TypeRef>) -> Result<Stri
     })
 }
 
+// The structs below are the context the templates are rendered with. Custom
+// templates (see [Options::template_dir]) rely on them, so fields are only ever
+// added, never renamed or removed. `jinja/README.md` describes them as well.
+
 #[derive(Debug, Clone, Serialize)]
-struct DataTypeContext {
-    name: String,
+pub(crate) struct DataTypeContext {
+    /// The Rust type, e.g. `Vec<crate::generated::types::foo::Bar>`.
+    pub(crate) name: String,
+    /// Arguments for `#[serde(...)]` which a field of this type needs.
     extra_serde_annotations: Vec<String>,
+    /// The named type which is embedded by value, as opposed to for example
+    /// behind an array.
+    #[serde(skip)]
+    direct_type_ref: Option<TypeRef>,
+    /// The function to deserialize with, see [bounded_deserializer].
+    deserialize_with: Option<String>,
+    /// Arguments for `#[schemars(...)]` which carry the schema constraints
+    /// over into the derived `JsonSchema`, see [Options::json_schema].
+    schemars_annotations: Vec<String>,
+    /// The proptest strategy for `name`, see [arbitrary_strategy]. `None`
+    /// for the config.
+    arbitrary_strategy: Option<String>,
+    /// The named types `name` refers to.
+    #[serde(skip)]
+    pub(crate) type_refs: BTreeSet<TypeRef>,
 }
 
+impl DataTypeContext {
+    /// A type which needs no annotations, e.g. of a config entry.
+    fn plain(name: &str) -> Self {
+        DataTypeContext {
+            name: name.to_string(),
+            extra_serde_annotations: Vec::new(),
+            direct_type_ref: None,
+            deserialize_with: None,
+            schemars_annotations: Vec::new(),
+            arbitrary_strategy: None,
+            type_refs: BTreeSet::new(),
+        }
+    }
+}
+
+/// An argument, result, variable, property or config entry.
 #[derive(Debug, Clone, Serialize)]
-struct ArgumentContext {
-    name: String,
-    description: Option<String>,
-    data_type: DataTypeContext,
+pub(crate) struct ArgumentContext {
+    /// The name as in the YAML. Use the `snake` filter for identifiers.
+    pub(crate) name: String,
+    pub(crate) description: Option<String>,
+    pub(crate) data_type: DataTypeContext,
+    /// The schema `default` as Rust expression, see [default_value].
+    pub(crate) default: Option<String>,
 }
 
 impl ArgumentContext {
-    pub fn from_schema(
+    fn from_schema(
         name: String,
         var: &Type,
+        options: &Options,
         type_refs: &mut BTreeSet<TypeRef>,
     ) -> Result<Self> {
+        let deserialize_with = bounded_deserializer(&var.arg, options);
+        let mut refs = BTreeSet::new();
+        let data_type = as_typename(&var.arg, None, options, &mut refs)?;
+        type_refs.extend(refs.iter().cloned());
         Ok(ArgumentContext {
             name,
             description: var.description.clone(),
             data_type: DataTypeContext {
-                name: as_typename(&var.arg, type_refs)?,
-                extra_serde_annotations: Vec::new(),
+                name: data_type,
+                extra_serde_annotations: deserialize_with
+                    .iter()
+                    .map(|d| format!("deserialize_with = \"{d}\""))
+                    .collect(),
+                direct_type_ref: None,
+                deserialize_with,
+                schemars_annotations: schemars_annotations(&var.arg, false),
+                arbitrary_strategy: Some(arbitrary_strategy(&var.arg, None, options)?),
+                type_refs: refs,
             },
+            default: default_value(&var.arg, None, options)?,
         })
     }
 }
 
 #[derive(Debug, Clone, Serialize)]
-struct CommandContext {
-    name: String,
-    description: String,
-    result: Option<ArgumentContext>,
-    arguments: Vec<ArgumentContext>,
+pub(crate) struct CommandContext {
+    pub(crate) name: String,
+    pub(crate) description: String,
+    /// The return value, always named `return_value`.
+    pub(crate) result: Option<ArgumentContext>,
+    pub(crate) arguments: Vec<ArgumentContext>,
 }
 
 impl CommandContext {
-    pub fn from_schema(
+    fn from_schema(
         name: String,
         cmd: &crate::schema::interface::Command,
+        options: &Options,
         type_refs: &mut BTreeSet<TypeRef>,
     ) -> Result<Self> {
         let mut arguments = Vec::new();
         for (name, arg) in &cmd.arguments {
-            arguments.push(ArgumentContext::from_schema(name.clone(), arg, type_refs)?);
+            arguments.push(
+                ArgumentContext::from_schema(name.clone(), arg, options, type_refs)
+                    .with_context(|| format!("In argument `{name}`"))?,
+            );
         }
         Ok(CommandContext {
             name,
             description: cmd.description.clone(),
             result: match &cmd.result {
                 None => None,
-                Some(arg) => Some(ArgumentContext::from_schema(
-                    "return_value".to_string(),
-                    arg,
-                    type_refs,
-                )?),
+                Some(arg) => Some(
+                    ArgumentContext::from_schema(
+                        "return_value".to_string(),
+                        arg,
+                        options,
+                        type_refs,
+                    )
+                    .context("In the result")?,
+                ),
             },
             arguments,
         })
@@ -249,58 +895,75 @@ impl CommandContext {
 }
 
 #[derive(Debug, Clone, Serialize)]
-struct InterfaceContext {
-    name: String,
-    description: String,
-    cmds: Vec<CommandContext>,
-    vars: Vec<ArgumentContext>,
+pub(crate) struct InterfaceContext {
+    /// The file name under `interfaces/`, without `.yaml`.
+    pub(crate) name: String,
+    pub(crate) description: String,
+    pub(crate) cmds: Vec<CommandContext>,
+    pub(crate) vars: Vec<ArgumentContext>,
+    /// Whether the module provides resp. requires this interface.
+    pub(crate) provided: bool,
+    pub(crate) required: bool,
 }
 
 impl InterfaceContext {
-    pub fn from_yaml(
+    fn from_yaml(
         yaml_repo: &mut YamlRepo,
         name: &str,
+        options: &Options,
         type_refs: &mut BTreeSet<TypeRef>,
     ) -> Result<Self> {
         let interface_yaml = yaml_repo.get_interface(name)?;
         let mut vars = Vec::new();
         for (name, var) in &interface_yaml.vars {
-            vars.push(ArgumentContext::from_schema(name.clone(), var, type_refs)?);
+            vars.push(
+                ArgumentContext::from_schema(name.clone(), var, options, type_refs)
+                    .with_context(|| format!("In variable `{name}`"))?,
+            );
         }
         let mut cmds = Vec::new();
         for (name, cmd) in &interface_yaml.cmds {
-            cmds.push(CommandContext::from_schema(name.clone(), cmd, type_refs)?);
+            cmds.push(
+                CommandContext::from_schema(name.clone(), cmd, options, type_refs)
+                    .with_context(|| format!("In command `{name}`"))?,
+            );
         }
         Ok(InterfaceContext {
             name: name.to_string(),
             description: interface_yaml.description.clone(),
             vars,
             cmds,
+            provided: false,
+            required: false,
         })
     }
 }
 
+/// A module below `types`. The module path of a type is the path of its
+/// types file, e.g. `/acme/powermeter#/Foo` lives in `types::acme::powermeter`.
 #[derive(Debug, Clone, Serialize, Default)]
 struct TypeModuleContext {
+    /// The submodules by name.
     children: BTreeMap<String, TypeModuleContext>,
     objects: Vec<ObjectTypeContext>,
     enums: Vec<EnumTypeContext>,
 }
 
 #[derive(Debug, Clone, Serialize)]
-struct ObjectTypeContext {
+pub(crate) struct ObjectTypeContext {
     name: String,
-    properties: Vec<ArgumentContext>,
+    pub(crate) properties: Vec<ArgumentContext>,
 }
 
 #[derive(Debug, Clone, Serialize)]
-struct EnumTypeContext {
+pub(crate) struct EnumTypeContext {
     name: String,
-    items: Vec<String>,
+    /// The variants, which are also their serialized names.
+    pub(crate) items: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
-enum TypeContext {
+pub(crate) enum TypeContext {
     Object(ObjectTypeContext),
     Enum(EnumTypeContext),
 }
@@ -308,6 +971,7 @@ enum TypeContext {
 fn type_context_from_ref(
     r: &TypeRef,
     yaml_repo: &mut YamlRepo,
+    options: &Options,
     type_refs: &mut BTreeSet<TypeRef>,
 ) -> Result<TypeContext> {
     use TypeBase::*;
@@ -315,32 +979,71 @@ fn type_context_from_ref(
 
     let data_types_yaml = yaml_repo.get_data_types(&r.module_path.join("/"))?;
 
-    let type_descr = data_types_yaml
-        .types
-        .get(&r.type_name)
-        .ok_or_else(|| anyhow!("Unable to find data type {:?}. Is it defined?", r))?;
+    let type_descr = data_types_yaml.types.get(&r.type_name).ok_or_else(|| {
+        anyhow!(
+            "Unable to find data type {:?}. Is it defined? Known types are: {}",
+            r,
+            data_types_yaml
+                .types
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })?;
+    let scope = Some(r.module_path.as_slice());
     match &type_descr.arg {
         Single(Object(args)) => {
             let mut properties = Vec::new();
             for (name, var) in &args.properties {
+                let context = || format!("In property `{name}`");
                 let mut extra_serde_annotations = Vec::new();
+                let default = default_value(&var.arg, scope, options).with_context(context)?;
+                let deserialize_with = bounded_deserializer(&var.arg, options);
+                let optional = !args.required.contains(name) && default.is_none();
+                let arbitrary_strategy = {
+                    let s = arbitrary_strategy(&var.arg, scope, options).with_context(context)?;
+                    if optional {
+                        optional_strategy(&s)
+                    } else {
+                        s
+                    }
+                };
+                let mut refs = BTreeSet::new();
                 let data_type = {
-                    let d = as_typename(&var.arg, type_refs)?;
-                    if !args.required.contains(name) {
+                    let d =
+                        as_typename(&var.arg, scope, options, &mut refs).with_context(context)?;
+                    // Properties with a default are filled in by serde.
+                    if optional {
                         extra_serde_annotations
                             .push("skip_serializing_if = \"Option::is_none\"".to_string());
+                        // Serde only treats missing fields as `None` without
+                        // a custom deserializer.
+                        if deserialize_with.is_some() {
+                            extra_serde_annotations.push("default".to_string());
+                        }
                         format!("Option<{}>", d)
                     } else {
                         d
                     }
                 };
+                if let Some(d) = &deserialize_with {
+                    extra_serde_annotations.push(format!("deserialize_with = \"{d}\""));
+                }
+                type_refs.extend(refs.iter().cloned());
                 properties.push(ArgumentContext {
                     name: name.clone(),
                     description: var.description.clone(),
                     data_type: DataTypeContext {
                         name: data_type,
                         extra_serde_annotations,
+                        direct_type_ref: direct_type_ref(&var.arg, scope).with_context(context)?,
+                        deserialize_with,
+                        schemars_annotations: schemars_annotations(&var.arg, optional),
+                        arbitrary_strategy: Some(arbitrary_strategy),
+                        type_refs: refs,
                     },
+                    default,
                 });
             }
             Ok(TypeContext::Object(ObjectTypeContext {
@@ -349,44 +1052,171 @@ fn type_context_from_ref(
             }))
         }
         Single(String(args)) => {
-            assert!(
-                args.enum_items.is_some(),
-                "Expected a named string type to be an enum, but {} was not.",
-                r.type_name
-            );
+            let Some(items) = &args.enum_items else {
+                bail!(
+                    "Expected a named string type to be an enum, but {} was not.",
+                    r.type_name
+                );
+            };
 
             Ok(TypeContext::Enum(EnumTypeContext {
                 name: r.type_name.clone(),
-                items: args.enum_items.clone().unwrap(),
+                items: items.clone(),
             }))
         }
-        other => unreachable!("Does not support $ref for {other:?}"),
+        other => bail!(
+            "Does not support $ref for {other:?}. Only objects and string enums can be named types."
+        ),
+    }
+}
+
+/// Breaks reference cycles between the `types` by boxing properties.
+///
+/// A property which embeds another type by value needs a [Box] if the
+/// embedded type (transitively) embeds the parent type again, otherwise the
+/// generated struct would be infinitely sized. References behind an array are
+/// already heap allocated and never need boxing.
+fn box_recursive_types(types: &mut BTreeMap<TypeRef, TypeContext>, options: &Options) {
+    let mut edges: BTreeMap<&TypeRef, BTreeSet<&TypeRef>> = BTreeMap::new();
+    for (type_ref, context) in types.iter() {
+        let TypeContext::Object(object) = context else {
+            continue;
+        };
+        edges.insert(
+            type_ref,
+            object
+                .properties
+                .iter()
+                .filter_map(|p| p.data_type.direct_type_ref.as_ref())
+                .collect(),
+        );
+    }
+
+    let reaches = |from: &TypeRef, to: &TypeRef| {
+        let mut seen = BTreeSet::new();
+        let mut stack = vec![from];
+        while let Some(current) = stack.pop() {
+            if current == to {
+                return true;
+            }
+            if seen.insert(current) {
+                stack.extend(edges.get(current).into_iter().flatten());
+            }
+        }
+        false
+    };
+
+    let mut boxed = BTreeSet::new();
+    for (type_ref, context) in types.iter() {
+        let TypeContext::Object(object) = context else {
+            continue;
+        };
+        for p in &object.properties {
+            match &p.data_type.direct_type_ref {
+                Some(target) if reaches(target, type_ref) => {
+                    boxed.insert((type_ref.clone(), p.name.clone()));
+                }
+                _ => (),
+            }
+        }
+    }
+
+    for (type_ref, name) in boxed {
+        let Some(TypeContext::Object(object)) = types.get_mut(&type_ref) else {
+            continue;
+        };
+        for p in object.properties.iter_mut().filter(|p| p.name == name) {
+            let target = p.data_type.direct_type_ref.as_ref().unwrap();
+            let path = target.absolute_type_path(options);
+            p.data_type.name = p.data_type.name.replacen(&path, &format!("Box<{path}>"), 1);
+            let strategy = format!("any_with::<{path}>(depth + 1)");
+            p.data_type.arbitrary_strategy = p
+                .data_type
+                .arbitrary_strategy
+                .as_ref()
+                .map(|s| s.replacen(&strategy, &format!("{strategy}.prop_map(Box::new)"), 1));
+        }
     }
 }
 
+/// An entry of `provides` or `requires` in the manifest.
 #[derive(Debug, Clone, Serialize)]
-struct SlotContext {
-    implementation_id: String,
-    interface: String,
+pub(crate) struct SlotContext {
+    pub(crate) implementation_id: String,
+    /// The name of the interface, see [InterfaceContext::name].
+    pub(crate) interface: String,
 }
 
+/// The config of a provided implementation.
 #[derive(Debug, Clone, Serialize)]
-struct ConfigContext {
-    name: String,
-    config: Vec<ArgumentContext>,
+pub(crate) struct ConfigContext {
+    /// The implementation id.
+    pub(crate) name: String,
+    pub(crate) config: Vec<ArgumentContext>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct PropertyContext {
+    pub(crate) name: String,
+    /// The name of the constant, see [constant_names].
+    pub(crate) constant: String,
+    /// The Rust type of the `value`.
+    pub(crate) data_type: String,
+    /// The value as Rust expression.
+    pub(crate) value: String,
 }
 
+impl PropertyContext {
+    fn from_schema(name: String, constant: String, value: &PropertyValue) -> Self {
+        let (data_type, value) = match value {
+            PropertyValue::Null => ("()", "()".to_string()),
+            PropertyValue::Boolean(v) => ("bool", v.to_string()),
+            PropertyValue::Integer(v) => ("i64", v.to_string()),
+            PropertyValue::Number(v) => ("f64", format!("{v:?}")),
+            PropertyValue::String(v) => ("&str", format!("{v:?}")),
+        };
+        PropertyContext {
+            name,
+            constant,
+            data_type: data_type.to_string(),
+            value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct PropertiesContext {
+    /// The implementation id.
+    pub(crate) name: String,
+    pub(crate) properties: Vec<PropertyContext>,
+}
+
+/// The root of the context for all templates.
 #[derive(Debug, Clone, Serialize)]
-struct RenderContext {
+pub(crate) struct RenderContext {
     /// The interfaces the user will need to fill in.
     provided_interfaces: Vec<InterfaceContext>,
     /// The interfaces we are requiring.
     required_interfaces: Vec<InterfaceContext>,
-    provides: Vec<SlotContext>,
-    requires: Vec<SlotContext>,
+    /// All provided and required interfaces, without duplicates.
+    pub(crate) interfaces: Vec<InterfaceContext>,
+    pub(crate) provides: Vec<SlotContext>,
+    pub(crate) requires: Vec<SlotContext>,
+    /// All types used by the interfaces, directly or transitively.
     types: TypeModuleContext,
-    module_config: Vec<ArgumentContext>,
-    provided_config: Vec<ConfigContext>,
+    /// The same types as `types`, by name.
+    #[serde(skip)]
+    pub(crate) named_types: BTreeMap<TypeRef, TypeContext>,
+    /// The config of the module itself.
+    pub(crate) module_config: Vec<ArgumentContext>,
+    /// The config of the provided implementations which have any.
+    pub(crate) provided_config: Vec<ConfigContext>,
+    /// The additional properties of the provided implementations.
+    pub(crate) provided_properties: Vec<PropertiesContext>,
+    /// Whether the code is for a module. Otherwise only the interfaces and
+    /// types are generated, see [Source::Interfaces].
+    has_module: bool,
+    options: Options,
 }
 
 fn title_case(arg: String) -> String {
@@ -397,16 +1227,100 @@ fn snake_case(arg: String) -> String {
     arg.to_case(Case::Snake)
 }
 
+fn screaming_snake_case(arg: String) -> String {
+    arg.to_case(Case::UpperSnake)
+}
+
+/// Whether `name` can be used as Rust identifier as is.
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && name != "_"
+}
+
+/// Returns the names of the constants for the properties `names`: In
+/// `SCREAMING_SNAKE_CASE`, with every character which may not appear in an
+/// identifier replaced by `_` and a `_` in front of a leading digit. If an
+/// earlier property got the same name already, `_2`, `_3`, ... is appended.
+fn constant_names<'a>(names: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+    let mut taken = HashSet::new();
+    names
+        .into_iter()
+        .map(|name| {
+            let mut base: String = screaming_snake_case(name.clone())
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            if base.is_empty() || base == "_" {
+                base = "PROPERTY".to_string();
+            } else if base.starts_with(|c: char| c.is_ascii_digit()) {
+                base.insert(0, '_');
+            }
+            let mut constant = base.clone();
+            for n in 2.. {
+                if taken.insert(constant.clone()) {
+                    break;
+                }
+                constant = format!("{base}_{n}");
+            }
+            constant
+        })
+        .collect()
+}
+
+/// Where a [TypeRef] was referenced first. Used to report the chain of
+/// references which lead to a broken type.
+#[derive(Debug, Clone)]
+enum Origin {
+    Interface(String),
+    Type(TypeRef),
+    /// Requested for being in its types file, see [emit_types_crate].
+    TypesFile,
+}
+
+fn reference_chain(t: &TypeRef, origins: &BTreeMap<TypeRef, Origin>) -> String {
+    let mut chain = vec![format!("{t:?}")];
+    let mut current = t;
+    loop {
+        match origins.get(current) {
+            Some(Origin::Type(parent)) => {
+                chain.push(format!("{parent:?}"));
+                current = parent;
+            }
+            Some(Origin::Interface(name)) => {
+                chain.push(format!("interface `{name}`"));
+                break;
+            }
+            Some(Origin::TypesFile) | None => break,
+        }
+    }
+    chain.reverse();
+    chain.join(" -> ")
+}
+
 fn handle_implementations(
     yaml_repo: &mut YamlRepo,
     entries: impl Iterator<Item = (String, String)>,
-    type_refs: &mut BTreeSet<TypeRef>,
+    options: &Options,
+    type_refs: &mut BTreeMap<TypeRef, Origin>,
 ) -> Result<(Vec<InterfaceContext>, Vec<SlotContext>)> {
     let mut implementations = Vec::new();
     let mut unique_interfaces = Vec::new();
     let mut seen_interfaces = HashSet::new();
     for (implementation_id, interface) in entries {
-        let interface_context = InterfaceContext::from_yaml(yaml_repo, &interface, type_refs)?;
+        let mut interface_refs = BTreeSet::new();
+        let interface_context =
+            InterfaceContext::from_yaml(yaml_repo, &interface, options, &mut interface_refs)
+                .with_context(|| {
+                    format!(
+                "While processing interface `{interface}` of implementation `{implementation_id}`"
+            )
+                })?;
+        for r in interface_refs {
+            type_refs
+                .entry(r)
+                .or_insert_with(|| Origin::Interface(interface.clone()));
+        }
 
         if !seen_interfaces.contains(&interface) {
             unique_interfaces.push(interface_context);
@@ -421,6 +1335,48 @@ fn handle_implementations(
     Ok((unique_interfaces, implementations))
 }
 
+/// Resolves the `type_refs` and all types they reference into the tree of
+/// type modules. Also returns the resolved types by name.
+fn resolve_types(
+    yaml_repo: &mut YamlRepo,
+    mut type_refs: BTreeMap<TypeRef, Origin>,
+    options: &Options,
+) -> Result<(TypeModuleContext, BTreeMap<TypeRef, TypeContext>)> {
+    let mut types = BTreeMap::new();
+    while types.len() != type_refs.len() {
+        let mut new = BTreeMap::new();
+        for t in type_refs.keys() {
+            if types.contains_key(t) {
+                continue;
+            }
+            let mut refs = BTreeSet::new();
+            let context = type_context_from_ref(t, yaml_repo, options, &mut refs)
+                .with_context(|| format!("While resolving {}", reference_chain(t, &type_refs)))?;
+            for r in refs {
+                new.entry(r).or_insert_with(|| Origin::Type(t.clone()));
+            }
+            types.insert(t.clone(), context);
+        }
+        for (r, origin) in new {
+            type_refs.entry(r).or_insert(origin);
+        }
+    }
+    box_recursive_types(&mut types, options);
+
+    let mut type_module_root = TypeModuleContext::default();
+    for (t, context) in &types {
+        let mut module = &mut type_module_root;
+        for p in &t.module_path {
+            module = module.children.entry(p.clone()).or_default();
+        }
+        match context {
+            TypeContext::Object(item) => module.objects.push(item.clone()),
+            TypeContext::Enum(item) => module.enums.push(item.clone()),
+        }
+    }
+    Ok((type_module_root, types))
+}
+
 /// Converts the config data read from yaml and generates the context for Jinja.
 ///
 /// The config data contains the config name (key) and the config data (value).
@@ -428,119 +1384,1771 @@ fn handle_implementations(
 fn emit_config(config: BTreeMap<String, ConfigEntry>) -> Vec<ArgumentContext> {
     config
         .into_iter()
-        .map(|(k, v)| match v.value {
-            ConfigEnum::Boolean(_) => ArgumentContext {
-                name: k,
-                description: v.description,
-                data_type: DataTypeContext {
-                    name: "bool".to_string(),
-                    extra_serde_annotations: Vec::new(),
-                },
-            },
-            ConfigEnum::Integer(_) => ArgumentContext {
-                name: k,
-                description: v.description,
-                data_type: DataTypeContext {
-                    name: "i64".to_string(),
-                    extra_serde_annotations: Vec::new(),
-                },
-            },
-            ConfigEnum::Number(_) => ArgumentContext {
-                name: k,
-                description: v.description,
-                data_type: DataTypeContext {
-                    name: "f64".to_string(),
-                    extra_serde_annotations: Vec::new(),
-                },
-            },
-            ConfigEnum::String(_) => ArgumentContext {
+        .map(|(k, v)| {
+            let name = match v.value {
+                ConfigEnum::Boolean(_) => "bool",
+                ConfigEnum::Integer(_) => "i64",
+                ConfigEnum::Number(_) => "f64",
+                ConfigEnum::String(_) => "String",
+            };
+            ArgumentContext {
                 name: k,
                 description: v.description,
-                data_type: DataTypeContext {
-                    name: "String".to_string(),
-                    extra_serde_annotations: Vec::new(),
-                },
-            },
+                data_type: DataTypeContext::plain(name),
+                default: None,
+            }
         })
         .collect::<Vec<_>>()
 }
 
-pub fn emit(manifest_path: PathBuf, everest_core: Vec<PathBuf>) -> Result<String> {
-    let mut yaml_repo = YamlRepo::new(everest_core);
-    let blob = fs::read_to_string(&manifest_path).context("While reading manifest file")?;
-    let manifest: Manifest = serde_yaml::from_str(&blob).context("While parsing manifest")?;
-
-    let mut env = Environment::new();
-    env.set_undefined_behavior(UndefinedBehavior::Strict);
-    env.add_filter("title", title_case);
-    env.add_filter("snake", snake_case);
-    env.add_template("client", CLIENT_JINJA)?;
-    env.add_template("config", CONFIG_JINJA)?;
-    env.add_template("module", MODULE_JINJA)?;
-    env.add_template("service", SERVICE_JINJA)?;
-    env.add_template("types", TYPES_JINJA)?;
+/// The file the module includes. It contains everything or, with
+/// [Options::split_modules], pulls in the module tree.
+pub const ROOT_FILE: &str = "generated.rs";
 
-    let provided_config = manifest
-        .provides
-        .iter()
-        .filter(|(_, data)| !data.config.is_empty())
-        .map(|(name, data)| ConfigContext {
+/// The directory which holds the module tree, see [Options::split_modules].
+pub const MODULE_TREE_DIR: &str = "generated";
+
+/// The result of [emit].
+#[derive(Debug)]
+pub struct Output {
+    /// The generated code by path relative to the output directory. The
+    /// module only includes [ROOT_FILE], which references all others.
+    pub files: BTreeMap<PathBuf, String>,
+    /// The manifest and every interface and types file the code depends on.
+    pub input_files: BTreeSet<PathBuf>,
+    /// Problems which did not prevent the code generation.
+    pub warnings: Vec<String>,
+}
+
+impl Output {
+    /// Returns the code of [ROOT_FILE].
+    pub fn root(&self) -> &str {
+        &self.files[Path::new(ROOT_FILE)]
+    }
+}
+
+/// The environment the templates are rendered with.
+pub(crate) struct Templates {
+    pub env: Environment<'static>,
+    /// The files of the custom templates, which are inputs as well.
+    pub files: BTreeSet<PathBuf>,
+}
+
+impl Templates {
+    /// Checks the `options` and sets up the built-in templates, replaced by or
+    /// complemented with the ones in [Options::template_dir].
+    pub(crate) fn new(options: &Options) -> Result<Self> {
+        options.validate()?;
+        let mut env = Environment::new();
+        env.set_undefined_behavior(UndefinedBehavior::Strict);
+        env.add_filter("title", title_case);
+        env.add_filter("snake", snake_case);
+        env.add_filter("screaming_snake", screaming_snake_case);
+        env.add_template("args", ARGS_JINJA)?;
+        env.add_template("client", CLIENT_JINJA)?;
+        env.add_template("config", CONFIG_JINJA)?;
+        env.add_template("docs_html", DOCS_HTML_JINJA)?;
+        env.add_template("docs_markdown", DOCS_MARKDOWN_JINJA)?;
+        env.add_template("fuzz_cargo", FUZZ_CARGO_JINJA)?;
+        env.add_template("fuzz_target", FUZZ_TARGET_JINJA)?;
+        env.add_template("generated", GENERATED_JINJA)?;
+        env.add_template("graph_dot", GRAPH_DOT_JINJA)?;
+        env.add_template("graph_mermaid", GRAPH_MERMAID_JINJA)?;
+        env.add_template("interface", INTERFACE_JINJA)?;
+        env.add_template("module", MODULE_JINJA)?;
+        env.add_template("scaffold_build", SCAFFOLD_BUILD_JINJA)?;
+        env.add_template("scaffold_cargo", SCAFFOLD_CARGO_JINJA)?;
+        env.add_template("scaffold_main", SCAFFOLD_MAIN_JINJA)?;
+        env.add_template("scaffold_manifest", SCAFFOLD_MANIFEST_JINJA)?;
+        env.add_template("service", SERVICE_JINJA)?;
+        env.add_template("types", TYPES_JINJA)?;
+        env.add_template("types_crate", TYPES_CRATE_JINJA)?;
+
+        let mut files = BTreeSet::new();
+        let Some(dir) = &options.template_dir else {
+            return Ok(Self { env, files });
+        };
+        let entries =
+            fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("jinja2") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let source = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            env.add_template_owned(name.to_string(), source)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            files.insert(path);
+        }
+        Ok(Self { env, files })
+    }
+}
+
+/// Reads the manifest and everything it references and resolves it into the
+/// context the templates are rendered with.
+fn resolve_context(
+    manifest_path: &Path,
+    yaml_repo: &mut YamlRepo,
+    options: Options,
+) -> Result<RenderContext> {
+    let manifest: Manifest = parse_yaml(manifest_path)?;
+    resolve_manifest(manifest, yaml_repo, options)
+}
+
+/// Like [resolve_context], for a manifest which is already parsed.
+fn resolve_manifest(
+    manifest: Manifest,
+    yaml_repo: &mut YamlRepo,
+    options: Options,
+) -> Result<RenderContext> {
+    let provided_config = manifest
+        .provides
+        .iter()
+        .filter(|(_, data)| !data.config.is_empty())
+        .map(|(name, data)| ConfigContext {
             name: name.clone(),
             config: emit_config(data.config.clone()),
         })
         .collect::<Vec<_>>();
 
-    let mut type_refs = BTreeSet::new();
+    let provided_properties = manifest
+        .provides
+        .iter()
+        .filter(|(_, data)| !data.properties.is_empty())
+        .map(|(name, data)| PropertiesContext {
+            name: name.clone(),
+            properties: data
+                .properties
+                .iter()
+                .zip(constant_names(data.properties.keys()))
+                .map(|((k, v), constant)| PropertyContext::from_schema(k.clone(), constant, v))
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut type_refs = BTreeMap::new();
     let (provided_interfaces, provides) = handle_implementations(
-        &mut yaml_repo,
+        yaml_repo,
         manifest
             .provides
             .into_iter()
             .map(|(name, imp)| (name, imp.interface)),
+        &options,
         &mut type_refs,
     )?;
     let (required_interfaces, requires) = handle_implementations(
-        &mut yaml_repo,
+        yaml_repo,
         manifest
             .requires
             .into_iter()
             .map(|(name, imp)| (name, imp.interface)),
+        &options,
         &mut type_refs,
     )?;
 
-    let mut type_module_root = TypeModuleContext::default();
+    let (types, named_types) = resolve_types(yaml_repo, type_refs, &options)?;
 
-    let mut done: BTreeSet<TypeRef> = BTreeSet::new();
-    while done.len() != type_refs.len() {
-        let mut new = BTreeSet::new();
-        for t in &type_refs {
-            if done.contains(t) {
-                continue;
-            }
-            let mut module = &mut type_module_root;
-            for p in &t.module_path {
-                module = module.children.entry(p.clone()).or_default();
-            }
-            match type_context_from_ref(t, &mut yaml_repo, &mut new)? {
-                TypeContext::Object(item) => module.objects.push(item),
-                TypeContext::Enum(item) => module.enums.push(item),
-            }
-            done.insert(t.clone());
-        }
-        type_refs.extend(new.into_iter());
+    let module_config = emit_config(manifest.config);
+
+    let mut provided_interfaces = provided_interfaces;
+    let mut required_interfaces = required_interfaces;
+    let provided_names: HashSet<_> = provided_interfaces.iter().map(|i| i.name.clone()).collect();
+    let required_names: HashSet<_> = required_interfaces.iter().map(|i| i.name.clone()).collect();
+    for interface in provided_interfaces
+        .iter_mut()
+        .chain(required_interfaces.iter_mut())
+    {
+        interface.provided = provided_names.contains(&interface.name);
+        interface.required = required_names.contains(&interface.name);
     }
 
-    let module_config = emit_config(manifest.config);
+    let mut interfaces = provided_interfaces.clone();
+    for interface in &required_interfaces {
+        if !interfaces.iter().any(|i| i.name == interface.name) {
+            interfaces.push(interface.clone());
+        }
+    }
 
-    let context = RenderContext {
+    Ok(RenderContext {
         provided_interfaces,
         required_interfaces,
+        interfaces,
         provides,
         requires,
-        types: type_module_root,
+        types,
+        named_types,
         module_config,
         provided_config,
+        provided_properties,
+        has_module: true,
+        options,
+    })
+}
+
+/// Resolves the `names` interfaces into a context without a module. Every
+/// interface is both provided and required, so that service and client are
+/// generated.
+fn resolve_interfaces_context(
+    names: &[String],
+    yaml_repo: &mut YamlRepo,
+    options: Options,
+) -> Result<RenderContext> {
+    let mut type_refs = BTreeMap::new();
+    let (mut interfaces, _) = handle_implementations(
+        yaml_repo,
+        names.iter().map(|name| (name.clone(), name.clone())),
+        &options,
+        &mut type_refs,
+    )?;
+    for interface in &mut interfaces {
+        interface.provided = true;
+        interface.required = true;
+    }
+    let (types, named_types) = resolve_types(yaml_repo, type_refs, &options)?;
+
+    Ok(RenderContext {
+        provided_interfaces: interfaces.clone(),
+        required_interfaces: interfaces.clone(),
+        interfaces,
+        provides: Vec::new(),
+        requires: Vec::new(),
+        types,
+        named_types,
+        module_config: Vec::new(),
+        provided_config: Vec::new(),
+        provided_properties: Vec::new(),
+        has_module: false,
+        options,
+    })
+}
+
+/// Formats `code` like rustfmt would. Code which does not parse is kept as
+/// is, the compiler will point out the problem better than we could.
+fn format_code(path: &Path, code: String, warnings: &mut Vec<String>) -> String {
+    match syn::parse_file(&code) {
+        Ok(file) => prettyplease::unparse(&file),
+        Err(err) => {
+            warnings.push(format!("Failed to format {}: {err}", path.display()));
+            code
+        }
+    }
+}
+
+/// Returns `path` inside of `out_dir` as string literal for `#[path]`. The
+/// root file is included into the module crate, so relative paths would be
+/// resolved against the wrong directory.
+fn path_literal(out_dir: &Path, path: &Path) -> String {
+    format!("{:?}", out_dir.join(path).display().to_string())
+}
+
+/// The result of [resolve].
+pub(crate) struct Resolved {
+    /// The module with all interfaces and types it uses.
+    pub context: RenderContext,
+    /// The manifest the `context` was resolved from, if any.
+    pub manifest: Option<Manifest>,
+    /// The manifest and every interface and types file the module depends on.
+    pub input_files: BTreeSet<PathBuf>,
+    /// Problems which did not prevent the resolution.
+    pub warnings: Vec<String>,
+}
+
+/// Reads the `source` and resolves all interfaces and types it references
+/// into the context the templates are rendered with.
+pub(crate) fn resolve(
+    source: Source,
+    yaml_repo: &mut YamlRepo,
+    options: Options,
+) -> Result<Resolved> {
+    let mut input_files = BTreeSet::new();
+    let (context, manifest) = match source {
+        Source::Manifest(manifest_path) => {
+            let manifest: Manifest = parse_yaml(&manifest_path)?;
+            let context = resolve_manifest(manifest.clone(), yaml_repo, options)?;
+            input_files.insert(manifest_path);
+            (context, Some(manifest))
+        }
+        Source::Interfaces(names) => (
+            resolve_interfaces_context(&names, yaml_repo, options)?,
+            None,
+        ),
+        Source::None => bail!("Either a manifest or interfaces are required"),
+    };
+    input_files.extend(yaml_repo.files().iter().cloned());
+    Ok(Resolved {
+        context,
+        manifest,
+        input_files,
+        warnings: yaml_repo.warnings().to_vec(),
+    })
+}
+
+/// Returns the manifest and every interface and types file the code
+/// generated for it depends on, including the custom templates.
+pub fn input_files(
+    source: Source,
+    everest_core: Vec<SearchRoot>,
+    options: Options,
+) -> Result<BTreeSet<PathBuf>> {
+    let templates = Templates::new(&options)?;
+    let mut yaml_repo = YamlRepo::new(everest_core);
+    let mut input_files = resolve(source, &mut yaml_repo, options)?.input_files;
+    input_files.extend(templates.files);
+    Ok(input_files)
+}
+
+/// Generates the code for the `source`. `out_dir` is the absolute path the
+/// files of [Output::files] will be written to.
+pub fn emit(
+    source: Source,
+    everest_core: Vec<SearchRoot>,
+    options: Options,
+    out_dir: &Path,
+) -> Result<Output> {
+    let templates = Templates::new(&options)?;
+    let Resolved {
+        context,
+        mut input_files,
+        mut warnings,
+        ..
+    } = resolve(source, &mut YamlRepo::new(everest_core), options)?;
+
+    let env = &templates.env;
+    let base = serde_json::to_value(&context)?;
+    // Renders the template `name` with the context and the additional `vars`.
+    let render = |name: &str, vars: Vec<(&str, serde_json::Value)>| -> Result<String> {
+        let mut context = base.clone();
+        for (key, value) in vars {
+            context[key] = value;
+        }
+        env.get_template(name)?
+            .render(context)
+            .with_context(|| format!("Failed to render the `{name}` template"))
     };
-    let tmpl = env.get_template("module").unwrap();
-    Ok(tmpl.render(context).unwrap())
+
+    let mut files = BTreeMap::new();
+    let tree = if context.options.split_modules {
+        let dir = Path::new(MODULE_TREE_DIR);
+        let mut types = BTreeMap::new();
+        let type_modules = match context.options.types_crate {
+            Some(_) => BTreeMap::new(),
+            None => context.types.children.clone(),
+        };
+        for (name, module) in &type_modules {
+            let path = dir.join("types").join(format!("{name}.rs"));
+            let code = render("types", vec![("types", serde_json::to_value(module)?)])?;
+            types.insert(name.clone(), path_literal(out_dir, &path));
+            files.insert(path, code);
+        }
+
+        // The files live in child modules of the root, so they see each
+        // other through the glob imports of the root.
+        let mut interfaces = BTreeMap::new();
+        for interface in &context.interfaces {
+            let name = snake_case(interface.name.clone());
+            let path = dir.join("interfaces").join(format!("{name}.rs"));
+            let code = render(
+                "interface",
+                vec![("trait", serde_json::to_value(interface)?)],
+            )?;
+            interfaces.insert(name, path_literal(out_dir, &path));
+            files.insert(path, format!("use super::*;\n{code}"));
+        }
+
+        let mut module = serde_json::Value::Null;
+        if context.has_module {
+            let path = dir.join("module.rs");
+            let code = render("module", Vec::new())?;
+            module = path_literal(out_dir, &path).into();
+            files.insert(path, format!("use super::*;\n{code}"));
+        }
+
+        serde_json::json!({
+            "types": types,
+            "interfaces": interfaces,
+            "module": module,
+        })
+    } else {
+        serde_json::Value::Null
+    };
+    files.insert(
+        PathBuf::from(ROOT_FILE),
+        render("generated", vec![("files", tree)])?,
+    );
+
+    let files = files
+        .into_iter()
+        .map(|(path, code)| {
+            let code = format_code(&path, code, &mut warnings);
+            (path, code)
+        })
+        .collect();
+
+    input_files.extend(templates.files);
+    Ok(Output {
+        files,
+        input_files,
+        warnings,
+    })
+}
+
+/// Generates the `src/lib.rs` of a crate with every type of the search roots.
+/// `options.types_crate` is the name of this crate.
+pub fn emit_types_crate(everest_core: Vec<SearchRoot>, options: Options) -> Result<Output> {
+    let templates = Templates::new(&options)?;
+    let mut yaml_repo = YamlRepo::new(everest_core);
+    let mut type_refs = BTreeMap::new();
+    for name in yaml_repo.data_types_names()? {
+        let module_path: Vec<_> = name.split('/').map(|s| s.to_string()).collect();
+        let data_types = yaml_repo.get_data_types(&name)?;
+        for type_name in data_types.types.keys() {
+            let t = TypeRef {
+                module_path: module_path.clone(),
+                type_name: type_name.clone(),
+            };
+            type_refs.insert(t, Origin::TypesFile);
+        }
+    }
+    let (types, _) = resolve_types(&mut yaml_repo, type_refs, &options)?;
+
+    let env = &templates.env;
+    let code = env
+        .get_template("types_crate")?
+        .render(serde_json::json!({
+            "types": types,
+            "options": options,
+        }))
+        .context("Failed to render the `types_crate` template")?;
+
+    let path = PathBuf::from("src/lib.rs");
+    let mut warnings = yaml_repo.warnings().to_vec();
+    let code = format_code(&path, code, &mut warnings);
+
+    let mut input_files = yaml_repo.files().clone();
+    input_files.extend(templates.files);
+    Ok(Output {
+        files: BTreeMap::from([(path, code)]),
+        input_files,
+        warnings,
+    })
+}
+
+/// Returns the path of `to` relative to `from`. Both must be absolute.
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<_> = from.components().collect();
+    let to: Vec<_> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let path: PathBuf = std::iter::repeat(Component::ParentDir)
+        .take(from.len() - common)
+        .chain(to[common..].iter().cloned())
+        .collect();
+    if path.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        path
+    }
+}
+
+/// Returns the `{path, namespace}` of the search roots for the `build.rs`, as
+/// string literals with the paths relative to `dir`.
+fn search_root_literals(everest_core: &[SearchRoot], dir: &Path) -> Vec<serde_json::Value> {
+    everest_core
+        .iter()
+        .map(|root| {
+            let path = root
+                .path
+                .canonicalize()
+                .unwrap_or_else(|_| root.path.clone());
+            serde_json::json!({
+                "path": format!("{:?}", relative_path(dir, &path).display().to_string()),
+                "namespace": root.namespace.as_ref().map(|ns| format!("{ns:?}")),
+            })
+        })
+        .collect()
+}
+
+/// Returns the string literal of [Options::template_dir] relative to `dir`
+/// for the `build.rs`, and fails unless [Options::root_module] is the crate
+/// root, where the generated `src/main.rs` resp. fuzz target includes the
+/// code.
+fn build_script_options(options: &Options, dir: &Path) -> Result<Option<String>> {
+    if options.root_module != Options::default().root_module {
+        bail!(
+            "The generated code is included into the crate root, so the root module must be `crate::generated`"
+        );
+    }
+    let Some(template_dir) = &options.template_dir else {
+        return Ok(None);
+    };
+    let path = template_dir
+        .canonicalize()
+        .with_context(|| format!("Failed to read {}", template_dir.display()))?;
+    Ok(Some(format!(
+        "{:?}",
+        relative_path(dir, &path).display().to_string()
+    )))
+}
+
+/// Generates the `manifest.yaml`, `Cargo.toml`, `build.rs` and `src/main.rs`
+/// of a new module called `module_name`. The `main.rs` is rendered with the
+/// context of the new manifest and gets stubs for all callbacks. `out_dir` is
+/// the absolute path of the module, the `build.rs` refers to the search roots
+/// relative to it.
+pub fn emit_scaffold(
+    module_name: &str,
+    provides: Vec<SlotContext>,
+    requires: Vec<SlotContext>,
+    authors: Vec<String>,
+    everest_core: Vec<SearchRoot>,
+    options: Options,
+    out_dir: &Path,
+) -> Result<Output> {
+    let templates = Templates::new(&options)?;
+    if module_name.is_empty() || !module_name.chars().all(|c| c.is_ascii_alphanumeric()) {
+        bail!("The module name `{module_name}` must be alphanumeric, e.g. `RsFooBar`");
+    }
+    if provides.is_empty() {
+        bail!("A module must provide at least one implementation");
+    }
+    let env = &templates.env;
+
+    let manifest = env
+        .get_template("scaffold_manifest")?
+        .render(serde_json::json!({
+            "module_name": module_name,
+            "provides": provides,
+            "requires": requires,
+            "authors": authors,
+        }))
+        .context("Failed to render the `scaffold_manifest` template")?;
+    let parsed: Manifest = serde_yaml::from_str(&manifest)
+        .context("The `scaffold_manifest` template rendered an invalid manifest")?;
+
+    let template_dir = build_script_options(&options, out_dir)?;
+    let search_roots = search_root_literals(&everest_core, out_dir);
+    let mut yaml_repo = YamlRepo::new(everest_core);
+    let context = resolve_manifest(parsed, &mut yaml_repo, options)?;
+    let mut base = serde_json::to_value(&context)?;
+    base["module_name"] = module_name.into();
+    base["search_roots"] = search_roots.into();
+    base["manifest_path"] = format!("{:?}", "manifest.yaml").into();
+    base["template_dir"] = template_dir.into();
+    let render = |name: &str| -> Result<String> {
+        env.get_template(name)?
+            .render(&base)
+            .with_context(|| format!("Failed to render the `{name}` template"))
+    };
+
+    // The files are edited by hand, so they are not formatted, which would
+    // drop the comments and blank lines of the templates.
+    let files = BTreeMap::from([
+        (PathBuf::from("manifest.yaml"), manifest + "\n"),
+        (
+            PathBuf::from("Cargo.toml"),
+            render("scaffold_cargo")? + "\n",
+        ),
+        (PathBuf::from("build.rs"), render("scaffold_build")? + "\n"),
+        (
+            PathBuf::from("src/main.rs"),
+            render("scaffold_main")? + "\n",
+        ),
+    ]);
+
+    let mut input_files = yaml_repo.files().clone();
+    input_files.extend(templates.files);
+    Ok(Output {
+        files,
+        input_files,
+        warnings: yaml_repo.warnings().to_vec(),
+    })
+}
+
+/// Generates a cargo-fuzz crate for the module of `manifest_path`: A
+/// `Cargo.toml`, a `build.rs` and one target in `fuzz_targets/` for every
+/// provided implementation with commands and every requirement with
+/// variables. A target decodes its input as JSON like the runtime does and
+/// dispatches it under every name to a stub subscriber. `out_dir` is the
+/// absolute path of the crate, the `build.rs` refers to the manifest and the
+/// search roots relative to it.
+pub fn emit_fuzz_targets(
+    manifest_path: &Path,
+    everest_core: Vec<SearchRoot>,
+    options: Options,
+    out_dir: &Path,
+) -> Result<Output> {
+    let templates = Templates::new(&options)?;
+    let module_name = module_name(manifest_path)?;
+    let manifest_literal = format!(
+        "{:?}",
+        relative_path(out_dir, &manifest_path.canonicalize()?)
+            .display()
+            .to_string()
+    );
+    let template_dir = build_script_options(&options, out_dir)?;
+    let search_roots = search_root_literals(&everest_core, out_dir);
+    let mut yaml_repo = YamlRepo::new(everest_core);
+    let context = resolve_context(manifest_path, &mut yaml_repo, options)?;
+
+    let find = |interfaces: &[InterfaceContext], name: &str| {
+        interfaces
+            .iter()
+            .find(|interface| interface.name == name)
+            .cloned()
+            .with_context(|| format!("The interface `{name}` was not resolved"))
+    };
+    let target = |name: String, kind: &str, implementation_id: &str, interface| {
+        serde_json::json!({
+            "name": name,
+            "kind": kind,
+            "implementation_id": implementation_id,
+            "trait": interface,
+        })
+    };
+    let mut targets = BTreeMap::new();
+    for provide in &context.provides {
+        let interface = find(&context.provided_interfaces, &provide.interface)?;
+        if !interface.cmds.is_empty() {
+            let name = format!("{}_commands", provide.implementation_id);
+            let value = target(
+                name.clone(),
+                "commands",
+                &provide.implementation_id,
+                interface,
+            );
+            targets.insert(name, value);
+        }
+    }
+    for require in &context.requires {
+        let interface = find(&context.required_interfaces, &require.interface)?;
+        if !interface.vars.is_empty() {
+            let name = format!("{}_variables", require.implementation_id);
+            let value = target(
+                name.clone(),
+                "variables",
+                &require.implementation_id,
+                interface,
+            );
+            targets.insert(name, value);
+        }
+    }
+
+    let env = &templates.env;
+    let mut base = serde_json::to_value(&context)?;
+    base["module_name"] = module_name.into();
+    base["search_roots"] = search_roots.into();
+    base["manifest_path"] = manifest_literal.into();
+    base["template_dir"] = template_dir.into();
+    base["targets"] = targets.values().cloned().collect::<Vec<_>>().into();
+    let render = |name: &str, target: Option<&serde_json::Value>| -> Result<String> {
+        let mut context = base.clone();
+        if let Some(target) = target {
+            context["target"] = target.clone();
+        }
+        env.get_template(name)?
+            .render(context)
+            .with_context(|| format!("Failed to render the `{name}` template"))
+    };
+
+    let mut files = BTreeMap::from([
+        (
+            PathBuf::from("Cargo.toml"),
+            render("fuzz_cargo", None)? + "\n",
+        ),
+        (
+            PathBuf::from("build.rs"),
+            render("scaffold_build", None)? + "\n",
+        ),
+    ]);
+    for (name, target) in &targets {
+        let path = Path::new("fuzz_targets").join(format!("{name}.rs"));
+        files.insert(path, render("fuzz_target", Some(target))? + "\n");
+    }
+
+    let mut input_files = yaml_repo.files().clone();
+    input_files.insert(manifest_path.to_path_buf());
+    input_files.extend(templates.files);
+    Ok(Output {
+        files,
+        input_files,
+        warnings: yaml_repo.warnings().to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{roots, tree, Workspace};
+
+    const COUNTER_INTERFACE: &str = r#"
+description: Counts
+cmds:
+  add:
+    description: Adds to the counter
+    arguments:
+      value:
+        description: The value to add
+        type: integer
+    result:
+      description: The new count
+      type: integer
+vars:
+  count:
+    description: The current count
+    type: integer
+"#;
+
+    const COUNTER_MANIFEST: &str = r#"
+description: Counts
+provides:
+  main:
+    interface: counter
+    description: The counter
+requires:
+  other:
+    interface: counter
+metadata:
+  license: https://opensource.org/licenses/Apache-2.0
+  authors:
+    - Jane Doe
+"#;
+
+    const RECURSIVE_TYPES: &str = r#"
+description: Recursive types
+types:
+  Node:
+    description: Refers to itself
+    type: object
+    required: [me]
+    properties:
+      me:
+        type: object
+        $ref: /graph#/Node
+      next:
+        type: object
+        $ref: /graph#/Node
+      children:
+        type: array
+        items:
+          type: object
+          $ref: /graph#/Node
+  Ping:
+    description: Refers to Pong
+    type: object
+    required: [pong]
+    properties:
+      pong:
+        type: object
+        $ref: /graph#/Pong
+  Pong:
+    description: Refers to Ping
+    type: object
+    required: [ping, leaf]
+    properties:
+      ping:
+        type: object
+        $ref: /graph#/Ping
+      leaf:
+        type: object
+        $ref: /graph#/Leaf
+  Leaf:
+    description: Refers to nothing
+    type: object
+    properties:
+      value:
+        type: integer
+"#;
+
+    #[test]
+    fn recursive_types_are_boxed() {
+        let dir = tree(&[("types/graph.yaml", RECURSIVE_TYPES)]);
+        let options = Options {
+            types_crate: Some("everest_types".to_string()),
+            ..Options::default()
+        };
+        let out = emit_types_crate(roots(&dir), options).unwrap();
+        let code = &out.files[Path::new("src/lib.rs")];
+        let fields: BTreeSet<_> = code
+            .lines()
+            .filter_map(|line| line.trim().strip_prefix("pub "))
+            .collect();
+        for field in [
+            // Itself, directly and through an `Option`.
+            "me: Box<::everest_types::graph::Node>,",
+            "next: Option<Box<::everest_types::graph::Node>>,",
+            // A `Vec` is on the heap already.
+            "children: Option<Vec<::everest_types::graph::Node>>,",
+            // Each other.
+            "pong: Box<::everest_types::graph::Pong>,",
+            "ping: Box<::everest_types::graph::Ping>,",
+            // Not part of a cycle.
+            "leaf: ::everest_types::graph::Leaf,",
+        ] {
+            assert!(fields.contains(field), "`{field}` not in\n{code}");
+        }
+    }
+
+    const BOUNDED_INTERFACE: &str = r#"
+description: Bounded results
+cmds:
+  percent:
+    description: Returns a percentage
+    result:
+      description: The percentage
+      type: integer
+      minimum: 0
+      maximum: 100
+  levels:
+    description: Returns levels
+    arguments:
+      count:
+        description: How many levels
+        type: integer
+        minimum: 1
+        maximum: 10
+    result:
+      description: The levels
+      type: array
+      items:
+        type: integer
+        minimum: -5
+        maximum: 5
+"#;
+
+    const BOUNDED_MANIFEST: &str = r#"
+description: Calls commands with bounded results
+provides:
+  main:
+    interface: bounded
+    description: The bounded results
+requires:
+  other:
+    interface: bounded
+metadata:
+  license: https://opensource.org/licenses/Apache-2.0
+  authors:
+    - Jane Doe
+"#;
+
+    #[test]
+    fn bounded_results_compile() {
+        let dir = tree(&[
+            ("interfaces/bounded.yaml", BOUNDED_INTERFACE),
+            ("modules/RsBounded/manifest.yaml", BOUNDED_MANIFEST),
+        ]);
+        let manifest = dir.path().join("modules/RsBounded/manifest.yaml");
+        let workspace = Workspace::new(&["plain", "structs"]);
+        for (name, command_args_structs) in [("plain", false), ("structs", true)] {
+            let options = Options {
+                precise_integers: true,
+                command_args_structs,
+                ..Options::default()
+            };
+            let out = emit(
+                Source::Manifest(manifest.clone()),
+                roots(&dir),
+                options.clone(),
+                &workspace.src(name),
+            )
+            .unwrap();
+            let code = out.root();
+            for bounds in ["0, 100", "{ -5 }, 5"] {
+                let deserializer = format!("deserialize_bounded::<_, _, {bounds}>");
+                assert!(
+                    code.contains(&deserializer),
+                    "{deserializer} not in\n{code}"
+                );
+            }
+            workspace.module(name, &out, &options, "fn main() {}");
+        }
+        workspace.cargo(&["check", "--workspace"]);
+    }
+
+    #[test]
+    fn fuzz_targets_compile() {
+        let dir = tree(&[
+            ("interfaces/counter.yaml", COUNTER_INTERFACE),
+            ("modules/RsCounter/manifest.yaml", COUNTER_MANIFEST),
+        ]);
+        let manifest = dir.path().join("modules/RsCounter/manifest.yaml");
+        let options = Options {
+            types_crate: Some("counter_types".to_string()),
+            ..Options::default()
+        };
+        let workspace = Workspace::new(&["counter_types", "fuzz"]);
+        let out = emit_fuzz_targets(
+            &manifest,
+            roots(&dir),
+            options.clone(),
+            &workspace.path().join("fuzz"),
+        )
+        .unwrap();
+        let targets: Vec<_> = out
+            .files
+            .keys()
+            .filter(|path| path.starts_with("fuzz_targets"))
+            .collect();
+        assert_eq!(
+            targets,
+            [
+                Path::new("fuzz_targets/main_commands.rs"),
+                Path::new("fuzz_targets/other_variables.rs"),
+            ]
+        );
+        for (path, code) in &out.files {
+            workspace.write(Path::new("fuzz").join(path), code);
+        }
+        workspace.types_crate(roots(&dir), &options);
+        workspace.cargo(&["check", "--bin", "main_commands"]);
+    }
+
+    #[test]
+    fn properties_become_unique_constants() {
+        let manifest = r#"
+description: Has properties which are no identifiers
+provides:
+  main:
+    interface: counter
+    description: The counter
+    max-current: 16
+    maxCurrent: 32.5
+    max_current_2: true
+    3phase: yes
+    "-": null
+metadata:
+  license: https://opensource.org/licenses/Apache-2.0
+  authors:
+    - Jane Doe
+"#;
+        let dir = tree(&[
+            ("interfaces/counter.yaml", COUNTER_INTERFACE),
+            ("modules/RsCounter/manifest.yaml", manifest),
+        ]);
+        let workspace = Workspace::new(&["module"]);
+        let options = Options::default();
+        let out = emit(
+            Source::Manifest(dir.path().join("modules/RsCounter/manifest.yaml")),
+            roots(&dir),
+            options.clone(),
+            &workspace.src("module"),
+        )
+        .unwrap();
+        let main = r#"
+fn main() {
+    use generated::main_properties::*;
+    // `maxCurrent` comes before `max_current_2`.
+    let _: (&str, (), i64, f64, bool) = (_3_PHASE, PROPERTY, MAX_CURRENT, MAX_CURRENT_2, MAX_CURRENT_2_2);
+}
+"#;
+        workspace.module("module", &out, &options, main);
+        workspace.cargo(&["check"]);
+    }
+
+    #[test]
+    fn dispatch_names_the_invalid_argument() {
+        let dir = tree(&[
+            ("interfaces/counter.yaml", COUNTER_INTERFACE),
+            ("modules/RsCounter/manifest.yaml", COUNTER_MANIFEST),
+        ]);
+        let manifest = dir.path().join("modules/RsCounter/manifest.yaml");
+        let workspace = Workspace::new(&["plain", "structs"]);
+        for (name, command_args_structs, add) in [
+            (
+                "plain",
+                false,
+                "value: i64) -> ::everestrs::Result<i64> { Ok(value) }",
+            ),
+            (
+                "structs",
+                true,
+                "args: generated::CounterAddArgs) -> ::everestrs::Result<i64> { Ok(args.value) }",
+            ),
+        ] {
+            let options = Options {
+                command_args_structs,
+                ..Options::default()
+            };
+            let out = emit(
+                Source::Manifest(manifest.clone()),
+                roots(&dir),
+                options.clone(),
+                &workspace.src(name),
+            )
+            .unwrap();
+            let main = format!(
+                r##"
+struct Counter;
+
+impl generated::CounterServiceSubscriber for Counter {{
+    fn add(&self, _publishers: &generated::ModulePublisher, {add}
+}}
+
+fn main() {{
+    let publishers = generated::ModulePublisher::new(::everestrs::Runtime::detached());
+    for parameters in [r#"{{"value": 1}}"#, r#"{{"value": "one"}}"#, "{{}}"] {{
+        let parameters = ::serde_json::from_str(parameters).unwrap();
+        match generated::dispatch_command_to_counter(&publishers, &Counter, "add", parameters) {{
+            Ok(value) => println!("{{value}}"),
+            Err(err) => println!("{{err}}"),
+        }}
+    }}
+}}
+"##
+            );
+            workspace.module(name, &out, &options, &main);
+        }
+        for name in ["plain", "structs"] {
+            assert_eq!(
+                workspace.cargo(&["run", "-q", "--bin", name]),
+                "1\n\
+                 invalid argument to command call: 'value'\n\
+                 missing argument to command call: 'value'\n",
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn root_module_is_validated() {
+        let options = |root_module: &str| Options {
+            root_module: root_module.to_string(),
+            ..Options::default()
+        };
+        assert!(options("crate::generated").validate().is_ok());
+        assert!(options("crate::foo::generated").validate().is_ok());
+        for invalid in [
+            "",
+            "generated",
+            "crate",
+            "crate::",
+            "crate::foo",
+            "crate::foo-bar::generated",
+            "crate::super::generated",
+            "::generated",
+        ] {
+            assert!(options(invalid).validate().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn scaffold_forwards_the_options() {
+        let dir = tree(&[
+            ("interfaces/counter.yaml", COUNTER_INTERFACE),
+            ("templates/unused.jinja2", "Not included by any template"),
+        ]);
+        let options = Options {
+            command_args_structs: true,
+            precise_integers: true,
+            split_modules: true,
+            json_schema: true,
+            arbitrary: true,
+            template_dir: Some(dir.path().join("templates")),
+            types_crate: Some("counter_types".to_string()),
+            visibility: "pub".to_string(),
+            ..Options::default()
+        };
+        let workspace = Workspace::new(&["counter_types", "module"]);
+        let module_dir = workspace.path().join("module");
+        fs::create_dir_all(&module_dir).unwrap();
+        let slot = |id: &str| SlotContext {
+            implementation_id: id.to_string(),
+            interface: "counter".to_string(),
+        };
+        let out = emit_scaffold(
+            "RsCounter",
+            vec![slot("main")],
+            vec![slot("other")],
+            Vec::new(),
+            roots(&dir),
+            options.clone(),
+            &module_dir.canonicalize().unwrap(),
+        )
+        .unwrap();
+        let build = &out.files[Path::new("build.rs")];
+        for call in [
+            ".command_args_structs(true)",
+            ".precise_integers(true)",
+            ".split_modules(true)",
+            ".json_schema(true)",
+            ".arbitrary(true)",
+            ".template_dir(",
+            r#".types_crate("counter_types")"#,
+            r#".visibility("pub")"#,
+        ] {
+            assert!(build.contains(call), "`{call}` not in\n{build}");
+        }
+        let cargo = &out.files[Path::new("Cargo.toml")];
+        assert!(
+            cargo.contains("counter-types = { workspace = true }"),
+            "{cargo}"
+        );
+
+        for (path, content) in &out.files {
+            workspace.write(Path::new("module").join(path), content);
+        }
+        workspace.types_crate(roots(&dir), &options);
+        workspace.cargo(&["check", "--workspace"]);
+
+        let options = Options {
+            root_module: "crate::bindings::generated".to_string(),
+            ..Options::default()
+        };
+        let err = emit_scaffold(
+            "RsCounter",
+            vec![slot("main")],
+            Vec::new(),
+            Vec::new(),
+            roots(&dir),
+            options,
+            &module_dir,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("root module"), "{err}");
+    }
+
+    #[test]
+    fn scaffolded_module_lints_clean() {
+        let dir = tree(&[("interfaces/counter.yaml", COUNTER_INTERFACE)]);
+        let slot = |id: &str| SlotContext {
+            implementation_id: id.to_string(),
+            interface: "counter".to_string(),
+        };
+        let out_dir = dir.path().join("modules/RsCounter");
+        for authors in [Vec::new(), vec!["Jane Doe".to_string()]] {
+            let out = emit_scaffold(
+                "RsCounter",
+                vec![slot("main")],
+                vec![slot("other")],
+                authors,
+                roots(&dir),
+                Options::default(),
+                &out_dir,
+            )
+            .unwrap();
+            let manifest = out_dir.join("manifest.yaml");
+            fs::create_dir_all(&out_dir).unwrap();
+            fs::write(&manifest, &out.files[Path::new("manifest.yaml")]).unwrap();
+
+            let diagnostics = crate::lint::lint(Source::Manifest(manifest), roots(&dir)).unwrap();
+            assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        }
+    }
+
+    const SETTINGS_TYPES: &str = r#"
+description: Settings
+types:
+  Settings:
+    description: The settings
+    type: object
+    properties:
+      step:
+        type: integer
+        default: 5
+      mode:
+        type: string
+        $ref: /settings#/Mode
+        default: Fast
+      label:
+        type: string
+  Mode:
+    description: How fast to count
+    type: string
+    enum: [Fast, Slow]
+"#;
+
+    const SETTINGS_INTERFACE: &str = r#"
+description: Counts by settings
+cmds:
+  add:
+    description: Adds to the step
+    arguments:
+      value:
+        description: The value to add
+        type: integer
+        default: 1
+    result:
+      description: The new settings
+      type: object
+      $ref: /settings#/Settings
+"#;
+
+    #[test]
+    fn defaults_fill_in_missing_values() {
+        let dir = tree(&[
+            ("types/settings.yaml", SETTINGS_TYPES),
+            ("interfaces/counter.yaml", SETTINGS_INTERFACE),
+            ("modules/RsCounter/manifest.yaml", COUNTER_MANIFEST),
+        ]);
+        let workspace = Workspace::new(&["module"]);
+        let options = Options::default();
+        let out = emit(
+            Source::Manifest(dir.path().join("modules/RsCounter/manifest.yaml")),
+            roots(&dir),
+            options.clone(),
+            &workspace.src("module"),
+        )
+        .unwrap();
+        let main = r##"
+use generated::types::settings::Settings;
+
+struct Counter;
+
+impl generated::CounterServiceSubscriber for Counter {
+    fn add(&self, _publishers: &generated::ModulePublisher, value: i64) -> ::everestrs::Result<Settings> {
+        let mut settings: Settings = ::serde_json::from_str("{}").unwrap();
+        settings.step += value;
+        Ok(settings)
+    }
+}
+
+fn main() {
+    // The client takes the arguments with a default as `Option`.
+    let _: fn(&generated::CounterClientPublisher, Option<i64>) -> ::everestrs::Result<Settings> =
+        generated::CounterClientPublisher::add;
+
+    let publishers = generated::ModulePublisher::new(::everestrs::Runtime::detached());
+    for parameters in [r#"{"value": 2}"#, "{}"] {
+        let parameters = ::serde_json::from_str(parameters).unwrap();
+        let settings = generated::dispatch_command_to_counter(&publishers, &Counter, "add", parameters);
+        println!("{}", settings.unwrap());
+    }
+}
+"##;
+        workspace.module("module", &out, &options, main);
+        assert_eq!(
+            workspace.cargo(&["run", "-q"]),
+            "{\"mode\":\"Fast\",\"step\":7}\n{\"mode\":\"Fast\",\"step\":6}\n"
+        );
+    }
+
+    #[test]
+    fn errors_point_at_the_cause() {
+        let error = |files: &[(&str, &str)]| {
+            let dir = tree(files);
+            let err = resolve(
+                Source::Manifest(dir.path().join("modules/RsCounter/manifest.yaml")),
+                &mut YamlRepo::new(roots(&dir)),
+                Options::default(),
+            )
+            .err()
+            .unwrap();
+            (dir, format!("{err:#}"))
+        };
+        let missing = "
+description: Refers to a missing type
+types:
+  Settings:
+    description: The settings
+    type: object
+    properties:
+      mode:
+        type: string
+        $ref: /settings#/Missing
+";
+
+        // A file which is no valid YAML.
+        let (dir, err) = error(&[
+            ("interfaces/counter.yaml", "description: [Counts"),
+            ("modules/RsCounter/manifest.yaml", COUNTER_MANIFEST),
+        ]);
+        let path = dir.path().join("interfaces/counter.yaml");
+        assert!(err.contains(&path.display().to_string()), "{err}");
+        assert!(err.contains("line 1 column"), "{err}");
+
+        // A file which is missing.
+        let (_, err) = error(&[("modules/RsCounter/manifest.yaml", COUNTER_MANIFEST)]);
+        assert!(err.contains("`interfaces/counter.yaml`"), "{err}");
+
+        // A type which is missing, with the references leading to it.
+        let (_, err) = error(&[
+            ("types/settings.yaml", missing),
+            ("interfaces/counter.yaml", SETTINGS_INTERFACE),
+            ("modules/RsCounter/manifest.yaml", COUNTER_MANIFEST),
+        ]);
+        for part in [
+            "interface `counter` -> TypeRef /settings#/Settings -> TypeRef /settings#/Missing",
+            "Unable to find data type TypeRef /settings#/Missing",
+        ] {
+            assert!(err.contains(part), "`{part}` not in {err}");
+        }
+    }
+
+    #[test]
+    fn input_files_are_the_ones_read() {
+        let unit = "
+description: Units
+types:
+  Mode:
+    description: How fast to count
+    type: string
+    enum: [Fast, Slow]
+";
+        let settings = SETTINGS_TYPES.replace("/settings#/Mode", "/unit#/Mode");
+        let dir = tree(&[
+            ("types/settings.yaml", &settings),
+            ("types/unit.yaml", unit),
+            ("types/unused.yaml", unit),
+            ("interfaces/counter.yaml", SETTINGS_INTERFACE),
+            ("interfaces/unused.yaml", COUNTER_INTERFACE),
+            ("modules/RsCounter/manifest.yaml", COUNTER_MANIFEST),
+            ("templates/types.jinja2", "// No types"),
+        ]);
+        let options = Options {
+            template_dir: Some(dir.path().join("templates")),
+            ..Options::default()
+        };
+        let files = input_files(
+            Source::Manifest(dir.path().join("modules/RsCounter/manifest.yaml")),
+            roots(&dir),
+            options,
+        )
+        .unwrap();
+        let files: Vec<_> = files
+            .iter()
+            .map(|path| path.strip_prefix(dir.path()).unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            files,
+            [
+                "interfaces/counter.yaml",
+                "modules/RsCounter/manifest.yaml",
+                "templates/types.jinja2",
+                // `unit` only through `settings`.
+                "types/settings.yaml",
+                "types/unit.yaml",
+            ]
+        );
+    }
+
+    #[test]
+    fn roots_shadow_in_order_and_namespaced() {
+        let core = tree(&[
+            ("interfaces/counter.yaml", COUNTER_INTERFACE),
+            ("modules/RsCounter/manifest.yaml", COUNTER_MANIFEST),
+        ]);
+        let vendor = tree(&[(
+            "interfaces/counter.yaml",
+            &SETTINGS_INTERFACE.replace("/settings#", "/acme/settings#"),
+        )]);
+        let acme = tree(&[(
+            "types/settings.yaml",
+            &SETTINGS_TYPES.replace("/settings#", "/acme/settings#"),
+        )]);
+        let roots = vec![
+            SearchRoot {
+                path: vendor.path().to_path_buf(),
+                namespace: None,
+            },
+            SearchRoot {
+                path: acme.path().to_path_buf(),
+                namespace: Some("acme".to_string()),
+            },
+            SearchRoot {
+                path: core.path().to_path_buf(),
+                namespace: None,
+            },
+        ];
+        let workspace = Workspace::new(&["module"]);
+        let options = Options::default();
+        let out = emit(
+            Source::Manifest(core.path().join("modules/RsCounter/manifest.yaml")),
+            roots,
+            options.clone(),
+            &workspace.src("module"),
+        )
+        .unwrap();
+        let shadowing = format!(
+            "{} shadows {}",
+            vendor.path().join("interfaces/counter.yaml").display(),
+            core.path().join("interfaces/counter.yaml").display()
+        );
+        assert_eq!(out.warnings, [shadowing]);
+        assert!(out
+            .input_files
+            .contains(&acme.path().join("types/settings.yaml")));
+
+        // The command of the vendor interface returns the namespaced type.
+        let main = "
+fn main() {
+    let _: fn(&generated::CounterClientPublisher, Option<i64>) -> ::everestrs::Result<generated::types::acme::settings::Settings> =
+        generated::CounterClientPublisher::add;
+}
+";
+        workspace.module("module", &out, &options, main);
+        workspace.cargo(&["check"]);
+    }
+
+    #[test]
+    fn split_modules_compile() {
+        let dir = tree(&[
+            ("types/settings.yaml", SETTINGS_TYPES),
+            ("interfaces/counter.yaml", SETTINGS_INTERFACE),
+            ("modules/RsCounter/manifest.yaml", COUNTER_MANIFEST),
+        ]);
+        let workspace = Workspace::new(&["module"]);
+        let options = Options {
+            split_modules: true,
+            ..Options::default()
+        };
+        let out = emit(
+            Source::Manifest(dir.path().join("modules/RsCounter/manifest.yaml")),
+            roots(&dir),
+            options.clone(),
+            &workspace.src("module"),
+        )
+        .unwrap();
+        assert_eq!(
+            out.files.keys().collect::<Vec<_>>(),
+            [
+                "generated/interfaces/counter.rs",
+                "generated/module.rs",
+                "generated/types/settings.rs",
+                "generated.rs",
+            ]
+        );
+        // Formatted by us, not just as the templates left it.
+        for (path, code) in &out.files {
+            let file = syn::parse_file(code).unwrap();
+            assert_eq!(prettyplease::unparse(&file), *code, "{}", path.display());
+        }
+
+        let main = r##"
+struct Counter;
+
+impl generated::CounterServiceSubscriber for Counter {
+    fn add(&self, _publishers: &generated::ModulePublisher, value: i64) -> ::everestrs::Result<generated::types::settings::Settings> {
+        Ok(generated::types::settings::Settings { label: None, mode: generated::types::settings::Mode::Slow, step: value })
+    }
+}
+
+fn main() {
+    let publishers = generated::ModulePublisher::new(::everestrs::Runtime::detached());
+    let parameters = ::serde_json::from_str(r#"{"value": 3}"#).unwrap();
+    let settings = generated::dispatch_command_to_counter(&publishers, &Counter, "add", parameters);
+    println!("{}", settings.unwrap());
+}
+"##;
+        workspace.module("module", &out, &options, main);
+        assert_eq!(
+            workspace.cargo(&["run", "-q"]),
+            "{\"mode\":\"Slow\",\"step\":3}\n"
+        );
+    }
+
+    #[test]
+    fn custom_templates_replace_the_built_in_ones() {
+        let interface = r#"
+{% include "service" %}
+{% include "client" %}
+/// The commands of `{{ trait.name }}`.
+{{ options.visibility }} const {{ trait.name | screaming_snake }}_COMMANDS: &[&str] = &[{% for cmd in trait.cmds %}"{{ cmd.name }}",{% endfor %}];
+"#;
+        let dir = tree(&[
+            ("interfaces/counter.yaml", COUNTER_INTERFACE),
+            ("modules/RsCounter/manifest.yaml", COUNTER_MANIFEST),
+            ("templates/interface.jinja2", interface),
+            ("templates/notes.txt", "{% Not a template"),
+        ]);
+        let workspace = Workspace::new(&["module"]);
+        let options = Options {
+            template_dir: Some(dir.path().join("templates")),
+            ..Options::default()
+        };
+        let out = emit(
+            Source::Manifest(dir.path().join("modules/RsCounter/manifest.yaml")),
+            roots(&dir),
+            options.clone(),
+            &workspace.src("module"),
+        )
+        .unwrap();
+        let templates = dir.path().join("templates");
+        assert!(out
+            .input_files
+            .contains(&templates.join("interface.jinja2")));
+        assert!(!out.input_files.contains(&templates.join("notes.txt")));
+
+        // The other templates are the built-in ones.
+        let main = r#"
+fn main() {
+    let _: fn(&generated::CounterClientPublisher, i64) -> ::everestrs::Result<i64> =
+        generated::CounterClientPublisher::add;
+    println!("{:?}", generated::COUNTER_COMMANDS);
+}
+"#;
+        workspace.module("module", &out, &options, main);
+        assert_eq!(workspace.cargo(&["run", "-q"]), "[\"add\"]\n");
+
+        fs::write(templates.join("module.jinja2"), "{% if %}").unwrap();
+        let err = Templates::new(&options).err().unwrap();
+        assert!(
+            format!("{err:#}").contains(&templates.join("module.jinja2").display().to_string()),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn modules_share_the_types_crate() {
+        let unused = "
+description: Not used by any interface
+types:
+  Unused:
+    description: Still in the types crate
+    type: string
+    enum: [A]
+";
+        let dir = tree(&[
+            ("types/settings.yaml", SETTINGS_TYPES),
+            ("types/unused.yaml", unused),
+            ("interfaces/counter.yaml", SETTINGS_INTERFACE),
+            ("modules/RsCounter/manifest.yaml", COUNTER_MANIFEST),
+        ]);
+        let workspace = Workspace::new(&["settings_types", "first", "second"]);
+        let options = Options {
+            types_crate: Some("settings_types".to_string()),
+            ..Options::default()
+        };
+        workspace.types_crate(roots(&dir), &options);
+        for name in ["first", "second"] {
+            let out = emit(
+                Source::Manifest(dir.path().join("modules/RsCounter/manifest.yaml")),
+                roots(&dir),
+                options.clone(),
+                &workspace.src(name),
+            )
+            .unwrap();
+            assert!(!out.root().contains("struct Settings"), "{}", out.root());
+            let main = "
+fn main() {
+    // The same type as in every other module.
+    let settings: ::settings_types::settings::Settings = ::serde_json::from_str(\"{}\").unwrap();
+    let _: generated::types::settings::Settings = settings;
+    let _ = ::settings_types::unused::Unused::A;
+}
+";
+            workspace.module(name, &out, &options, main);
+        }
+        workspace.cargo(&["check", "--workspace"]);
+    }
+
+    #[test]
+    fn public_bindings_in_a_library() {
+        let dir = tree(&[
+            ("interfaces/counter.yaml", COUNTER_INTERFACE),
+            ("modules/RsCounter/manifest.yaml", COUNTER_MANIFEST),
+        ]);
+        let workspace = Workspace::new(&["bindings", "app"]);
+        let options = Options {
+            visibility: "pub".to_string(),
+            root_module: "crate::everest::generated".to_string(),
+            ..Options::default()
+        };
+        let out = emit(
+            Source::Manifest(dir.path().join("modules/RsCounter/manifest.yaml")),
+            roots(&dir),
+            options.clone(),
+            &workspace.src("bindings"),
+        )
+        .unwrap();
+        workspace.module("bindings", &out, &options, "fn main() {}");
+        workspace.write(
+            workspace.src("bindings").join("lib.rs"),
+            "pub mod everest {\n    include!(\"generated.rs\");\n}\n",
+        );
+        workspace.write(
+            "app/Cargo.toml",
+            "[package]\nname = \"app\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+             [dependencies]\nbindings = { workspace = true }\neverestrs = { workspace = true }\n",
+        );
+        workspace.write(
+            "app/src/main.rs",
+            "use bindings::everest::generated;
+
+fn main() {
+    let _: fn(&generated::CounterClientPublisher, i64) -> ::everestrs::Result<i64> =
+        generated::CounterClientPublisher::add;
+    let _ = generated::ModulePublisher::new;
+}
+",
+        );
+        workspace.cargo(&["check", "-p", "app"]);
+    }
+
+    #[test]
+    fn interface_bindings_compile() {
+        let dir = tree(&[("interfaces/counter.yaml", COUNTER_INTERFACE)]);
+        let workspace = Workspace::new(&["module"]);
+        let options = Options::default();
+        let out = emit(
+            Source::Interfaces(vec!["counter".to_string()]),
+            roots(&dir),
+            options.clone(),
+            &workspace.src("module"),
+        )
+        .unwrap();
+        for item in ["struct Module", "ModulePublisher"] {
+            assert!(!out.root().contains(item), "{item} in\n{}", out.root());
+        }
+        let main = r##"
+use generated::{CounterClientPublisher, CounterServicePublisher};
+
+struct Counter;
+
+impl generated::CounterServiceSubscriber for Counter {
+    fn add(&self, _publishers: &CounterServicePublisher, value: i64) -> ::everestrs::Result<i64> {
+        Ok(value + 1)
+    }
+}
+
+impl generated::CounterClientSubscriber for Counter {
+    fn on_count(&self, _publishers: &CounterClientPublisher, value: i64) {
+        println!("count {value}");
+    }
+}
+
+fn main() {
+    let runtime = ::everestrs::Runtime::detached();
+    let service = CounterServicePublisher::new("main", runtime.clone());
+    let parameters = ::serde_json::from_str(r#"{"value": 1}"#).unwrap();
+    let result = generated::dispatch_command_to_counter(&service, &Counter, "add", parameters);
+    println!("add {}", result.unwrap());
+    let client = CounterClientPublisher::new("other", runtime);
+    generated::dispatch_variable_to_counter(&client, &Counter, "count", 3.into()).unwrap();
+}
+"##;
+        workspace.module("module", &out, &options, main);
+        assert_eq!(workspace.cargo(&["run", "-q"]), "add 2\ncount 3\n");
+    }
+
+    const LIMITS_TYPES: &str = r#"
+description: Limits
+types:
+  Limits:
+    description: The limits
+    type: object
+    required: [current, phases]
+    properties:
+      current:
+        type: number
+        minimum: 0
+        maximum: 32
+      phases:
+        type: integer
+        minimum: 1
+        maximum: 3
+      label:
+        type: string
+        maxLength: 8
+        pattern: ^[a-z]+$
+      samples:
+        type: array
+        minItems: 1
+        maxItems: 4
+        items:
+          type: number
+"#;
+
+    const LIMITS_INTERFACE: &str = r#"
+description: Limits the current
+vars:
+  limits:
+    description: The current limits
+    type: object
+    $ref: /limits#/Limits
+"#;
+
+    /// Generates the code for the `counter` interface of [LIMITS_INTERFACE]
+    /// with `options` into the binary crate `module` of a new workspace.
+    fn limits_module(options: &Options, main: &str) -> Workspace {
+        let dir = tree(&[
+            ("types/limits.yaml", LIMITS_TYPES),
+            ("interfaces/counter.yaml", LIMITS_INTERFACE),
+            ("modules/RsCounter/manifest.yaml", COUNTER_MANIFEST),
+        ]);
+        let workspace = Workspace::new(&["module"]);
+        let out = emit(
+            Source::Manifest(dir.path().join("modules/RsCounter/manifest.yaml")),
+            roots(&dir),
+            options.clone(),
+            &workspace.src("module"),
+        )
+        .unwrap();
+        workspace.module("module", &out, options, main);
+        workspace
+    }
+
+    #[test]
+    fn json_schema_keeps_the_constraints() {
+        let options = Options {
+            json_schema: true,
+            ..Options::default()
+        };
+        let main = r#"
+fn main() {
+    let schema = ::schemars::schema_for!(generated::types::limits::Limits);
+    println!("{}", ::serde_json::to_string(&schema).unwrap());
+}
+"#;
+        let workspace = limits_module(&options, main);
+        let schema: serde_json::Value =
+            serde_json::from_str(&workspace.cargo(&["run", "-q"])).unwrap();
+        let properties = &schema["properties"];
+        for (property, key, value) in [
+            ("current", "minimum", serde_json::json!(0.0)),
+            ("current", "maximum", serde_json::json!(32.0)),
+            ("phases", "minimum", serde_json::json!(1)),
+            ("phases", "maximum", serde_json::json!(3)),
+            ("label", "maxLength", serde_json::json!(8)),
+            ("label", "pattern", serde_json::json!("^[a-z]+$")),
+            ("samples", "minItems", serde_json::json!(1)),
+            ("samples", "maxItems", serde_json::json!(4)),
+        ] {
+            assert_eq!(
+                properties[property][key], value,
+                "{property}.{key} in {schema}"
+            );
+        }
+        assert_eq!(schema["required"], serde_json::json!(["current", "phases"]));
+    }
+
+    #[test]
+    fn arbitrary_values_respect_the_schema() {
+        let options = Options {
+            arbitrary: true,
+            ..Options::default()
+        };
+        let main = r#"
+use ::proptest::prelude::*;
+use ::proptest::strategy::ValueTree;
+use generated::types::limits::Limits;
+
+fn main() {
+    let mut runner = ::proptest::test_runner::TestRunner::deterministic();
+    let (mut labels, mut samples) = (0, 0);
+    for _ in 0..256 {
+        let limits = any::<Limits>().new_tree(&mut runner).unwrap().current();
+        assert!((0.0..=32.0).contains(&limits.current), "{limits:?}");
+        assert!((1..=3).contains(&limits.phases), "{limits:?}");
+        if let Some(label) = &limits.label {
+            labels += 1;
+            assert!(label.len() <= 8 && label.bytes().all(|b| b.is_ascii_lowercase()), "{limits:?}");
+        }
+        if let Some(s) = &limits.samples {
+            samples += 1;
+            assert!((1..=4).contains(&s.len()), "{limits:?}");
+        }
+        // Every value is accepted by the deserializer.
+        let json = ::serde_json::to_string(&limits).unwrap();
+        ::serde_json::from_str::<Limits>(&json).unwrap();
+    }
+    // The optional properties are left out only sometimes.
+    assert!((1..256).contains(&labels) && (1..256).contains(&samples));
+    println!("ok");
+}
+"#;
+        let workspace = limits_module(&options, main);
+        assert_eq!(workspace.cargo(&["run", "-q"]), "ok\n");
+    }
 }
@@ -0,0 +1,722 @@
+//! Renders the interfaces, types and module manifests of the search roots as
+//! documentation pages.
+
+use crate::codegen::{
+    module_name, parse_yaml, Options, Output, SearchRoot, Source, Templates, TypeRef, YamlRepo,
+};
+use crate::schema::manifest::{ConfigEntry, ConfigEnum};
+use crate::schema::types::{TypeBase, TypeEnum};
+use crate::schema::{Interface, Manifest, Type};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// The output format of [docs].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DocsFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+impl std::str::FromStr for DocsFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "markdown" => Ok(Self::Markdown),
+            "html" => Ok(Self::Html),
+            _ => bail!("Unknown docs format `{s}`, expected `markdown` or `html`"),
+        }
+    }
+}
+
+impl DocsFormat {
+    fn template(self) -> &'static str {
+        match self {
+            Self::Markdown => "docs_markdown",
+            Self::Html => "docs_html",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+        }
+    }
+}
+
+/// A piece of the description of a type, linking to the page of a named
+/// type if `href` is set.
+#[derive(Debug, Serialize)]
+struct Segment {
+    text: String,
+    href: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Constraint {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct TypeDoc {
+    segments: Vec<Segment>,
+    constraints: Vec<Constraint>,
+}
+
+impl TypeDoc {
+    fn new(text: &str) -> Self {
+        Self {
+            segments: vec![Segment {
+                text: text.to_string(),
+                href: None,
+            }],
+            constraints: Vec::new(),
+        }
+    }
+
+    fn constraint(&mut self, name: &str, value: Option<impl ToString>) {
+        if let Some(value) = value {
+            self.constraints.push(Constraint {
+                name: name.to_string(),
+                value: value.to_string(),
+            });
+        }
+    }
+}
+
+/// An argument, a property or a config entry. The properties of inline
+/// objects follow their parent as separate fields named `<parent>.<name>`.
+#[derive(Debug, Serialize)]
+struct FieldDoc {
+    name: String,
+    description: Option<String>,
+    required: bool,
+    data_type: TypeDoc,
+}
+
+/// A result, variable or named type.
+#[derive(Debug, Serialize)]
+struct ValueDoc {
+    name: String,
+    description: Option<String>,
+    qos: Option<i64>,
+    data_type: TypeDoc,
+    properties: Vec<FieldDoc>,
+}
+
+#[derive(Debug, Serialize)]
+struct CommandDoc {
+    name: String,
+    description: String,
+    arguments: Vec<FieldDoc>,
+    result: Option<ValueDoc>,
+}
+
+#[derive(Debug, Serialize)]
+struct ProvidesDoc {
+    id: String,
+    interface: String,
+    href: String,
+    description: String,
+    config: Vec<FieldDoc>,
+}
+
+#[derive(Debug, Serialize)]
+struct RequiresDoc {
+    id: String,
+    interface: String,
+    href: String,
+    min_connections: i64,
+    max_connections: i64,
+}
+
+/// A link to a page from the index.
+#[derive(Debug, Serialize)]
+struct Entry {
+    name: String,
+    href: String,
+    summary: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum Page {
+    Index {
+        interfaces: Vec<Entry>,
+        types: Vec<Entry>,
+        modules: Vec<Entry>,
+    },
+    Interface {
+        name: String,
+        description: String,
+        cmds: Vec<CommandDoc>,
+        vars: Vec<ValueDoc>,
+        errors: Vec<String>,
+    },
+    Types {
+        name: String,
+        description: String,
+        types: Vec<ValueDoc>,
+    },
+    Module {
+        name: String,
+        description: String,
+        authors: Vec<String>,
+        license: String,
+        provides: Vec<ProvidesDoc>,
+        requires: Vec<RequiresDoc>,
+        config: Vec<FieldDoc>,
+        capabilities: Vec<String>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+struct PageContext<'a> {
+    /// The relative path from the page to the root of the docs, e.g. `../`.
+    root: String,
+    page: &'a Page,
+}
+
+/// Returns `to`, relative to the directory of the page `from`. Both are
+/// relative to the root of the docs.
+fn href(from: &str, to: &str) -> String {
+    format!("{}{to}", "../".repeat(from.matches('/').count()))
+}
+
+fn join(prefix: &str, name: &str) -> String {
+    if prefix.is_empty() {
+        name.to_string()
+    } else {
+        format!("{prefix}.{name}")
+    }
+}
+
+fn summary(description: &str) -> String {
+    description
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+/// Describes types for the page at `page`.
+struct Describer<'a> {
+    page: &'a str,
+    extension: &'static str,
+    /// The module path of the types file, to resolve local references.
+    scope: Option<&'a [String]>,
+}
+
+impl Describer<'_> {
+    fn link(&self, reference: &str) -> TypeDoc {
+        let segment = match TypeRef::from_reference(reference, self.scope) {
+            Ok(r) => Segment {
+                href: Some(format!(
+                    "{}#{}",
+                    href(
+                        self.page,
+                        &format!("types/{}.{}", r.module_path.join("/"), self.extension)
+                    ),
+                    r.type_name
+                )),
+                text: r.type_name,
+            },
+            Err(_) => Segment {
+                text: reference.to_string(),
+                href: None,
+            },
+        };
+        TypeDoc {
+            segments: vec![segment],
+            constraints: Vec::new(),
+        }
+    }
+
+    /// Describes `t` and appends the properties of inline objects, named
+    /// relative to `prefix`, to `fields`.
+    fn describe(&self, t: &Type, prefix: &str, fields: &mut Vec<FieldDoc>) -> TypeDoc {
+        match &t.arg {
+            TypeBase::Single(t) => self.describe_enum(t, prefix, fields),
+            TypeBase::Multiple(types) => {
+                let mut doc = TypeDoc::default();
+                for (index, t) in types.iter().enumerate() {
+                    if index > 0 {
+                        doc.segments.push(Segment {
+                            text: " or ".to_string(),
+                            href: None,
+                        });
+                    }
+                    let inner = self.describe_enum(t, prefix, fields);
+                    doc.segments.extend(inner.segments);
+                    doc.constraints.extend(inner.constraints);
+                }
+                doc
+            }
+        }
+    }
+
+    fn describe_enum(&self, t: &TypeEnum, prefix: &str, fields: &mut Vec<FieldDoc>) -> TypeDoc {
+        match t {
+            TypeEnum::Null => TypeDoc::new("null"),
+            TypeEnum::Boolean(args) => {
+                let mut doc = TypeDoc::new("boolean");
+                doc.constraint("default", args.default);
+                doc
+            }
+            TypeEnum::String(args) => {
+                let mut doc = match &args.object_reference {
+                    Some(r) => self.link(r),
+                    None => TypeDoc::new("string"),
+                };
+                doc.constraint(
+                    "enum",
+                    args.enum_items.as_ref().map(|items| items.join(", ")),
+                );
+                doc.constraint("pattern", args.pattern.as_ref());
+                doc.constraint("format", args.format.as_ref().map(|_| "date-time"));
+                doc.constraint("minLength", args.min_length);
+                doc.constraint("maxLength", args.max_length);
+                doc.constraint("default", args.default.as_ref().map(|d| format!("{d:?}")));
+                doc
+            }
+            TypeEnum::Number(args) => {
+                let mut doc = TypeDoc::new("number");
+                doc.constraint("minimum", args.minimum);
+                doc.constraint("maximum", args.maximum);
+                doc.constraint("default", args.default);
+                doc
+            }
+            TypeEnum::Integer(args) => {
+                let mut doc = TypeDoc::new("integer");
+                doc.constraint("minimum", args.minimum);
+                doc.constraint("maximum", args.maximum);
+                doc.constraint("default", args.default);
+                doc
+            }
+            TypeEnum::Array(args) => {
+                let mut doc = TypeDoc::new("array");
+                if let Some(items) = &args.items {
+                    let inner = self.describe(items, &format!("{prefix}[]"), fields);
+                    doc.segments[0].text.push_str(" of ");
+                    doc.segments.extend(inner.segments);
+                    doc.constraints
+                        .extend(inner.constraints.into_iter().map(|c| Constraint {
+                            name: format!("items.{}", c.name),
+                            value: c.value,
+                        }));
+                }
+                doc.constraint("minItems", args.min_items);
+                doc.constraint("maxItems", args.max_items);
+                doc
+            }
+            TypeEnum::Object(args) => {
+                if let Some(r) = &args.object_reference {
+                    return self.link(r);
+                }
+                let mut doc = TypeDoc::new("object");
+                doc.constraint(
+                    "additionalProperties",
+                    args.additional_properties.then_some(true),
+                );
+                for (name, property) in &args.properties {
+                    self.field(
+                        &join(prefix, name),
+                        property,
+                        args.required.contains(name),
+                        fields,
+                    );
+                }
+                doc
+            }
+        }
+    }
+
+    /// Appends the field `name` and the properties of its inline objects.
+    fn field(&self, name: &str, t: &Type, required: bool, fields: &mut Vec<FieldDoc>) {
+        let mut properties = Vec::new();
+        let data_type = self.describe(t, name, &mut properties);
+        fields.push(FieldDoc {
+            name: name.to_string(),
+            description: t.description.clone(),
+            required,
+            data_type,
+        });
+        fields.extend(properties);
+    }
+
+    fn value(&self, name: &str, t: &Type) -> ValueDoc {
+        let mut properties = Vec::new();
+        let data_type = self.describe(t, "", &mut properties);
+        ValueDoc {
+            name: name.to_string(),
+            description: t.description.clone(),
+            qos: t.qos,
+            data_type,
+            properties,
+        }
+    }
+
+    fn config(&self, config: &BTreeMap<String, ConfigEntry>) -> Vec<FieldDoc> {
+        config
+            .iter()
+            .map(|(name, entry)| {
+                let (t, has_default) = match &entry.value {
+                    ConfigEnum::Boolean(args) => {
+                        (TypeEnum::Boolean(args.clone()), args.default.is_some())
+                    }
+                    ConfigEnum::String(args) => {
+                        (TypeEnum::String(args.clone()), args.default.is_some())
+                    }
+                    ConfigEnum::Integer(args) => {
+                        (TypeEnum::Integer(args.clone()), args.default.is_some())
+                    }
+                    ConfigEnum::Number(args) => {
+                        (TypeEnum::Number(args.clone()), args.default.is_some())
+                    }
+                };
+                FieldDoc {
+                    name: name.clone(),
+                    description: entry.description.clone(),
+                    required: !has_default,
+                    data_type: self.describe_enum(&t, name, &mut Vec::new()),
+                }
+            })
+            .collect()
+    }
+}
+
+fn interface_page(name: &str, interface: &Interface, path: &str, extension: &'static str) -> Page {
+    let describer = Describer {
+        page: path,
+        extension,
+        scope: None,
+    };
+    let cmds = interface
+        .cmds
+        .iter()
+        .map(|(cmd_name, cmd)| {
+            let mut arguments = Vec::new();
+            for (arg_name, arg) in &cmd.arguments {
+                describer.field(arg_name, arg, !arg.has_default(), &mut arguments);
+            }
+            CommandDoc {
+                name: cmd_name.clone(),
+                description: cmd.description.clone(),
+                arguments,
+                result: cmd.result.as_ref().map(|r| describer.value("result", r)),
+            }
+        })
+        .collect();
+    Page::Interface {
+        name: name.to_string(),
+        description: interface.description.clone(),
+        cmds,
+        vars: interface
+            .vars
+            .iter()
+            .map(|(var_name, var)| describer.value(var_name, var))
+            .collect(),
+        errors: interface
+            .errors
+            .iter()
+            .map(|e| e.reference.clone())
+            .collect(),
+    }
+}
+
+fn module_page(name: &str, manifest: &Manifest, path: &str, extension: &'static str) -> Page {
+    let describer = Describer {
+        page: path,
+        extension,
+        scope: None,
+    };
+    let interface_href =
+        |interface: &str| href(path, &format!("interfaces/{interface}.{extension}"));
+    Page::Module {
+        name: name.to_string(),
+        description: manifest.description.clone(),
+        authors: manifest.metadata.authors.clone(),
+        license: manifest.metadata.license.clone(),
+        provides: manifest
+            .provides
+            .iter()
+            .map(|(id, provides)| ProvidesDoc {
+                id: id.clone(),
+                interface: provides.interface.clone(),
+                href: interface_href(&provides.interface),
+                description: provides.description.clone(),
+                config: describer.config(&provides.config),
+            })
+            .collect(),
+        requires: manifest
+            .requires
+            .iter()
+            .map(|(id, requires)| RequiresDoc {
+                id: id.clone(),
+                interface: requires.interface.clone(),
+                href: interface_href(&requires.interface),
+                min_connections: requires.min_connections.unwrap_or(1),
+                max_connections: requires.max_connections.unwrap_or(1),
+            })
+            .collect(),
+        config: describer.config(&manifest.config),
+        capabilities: manifest.capabilities.clone(),
+    }
+}
+
+/// Renders one page per interface, types file and module manifest of the
+/// search roots, plus an index. A manifest given as `source` is documented
+/// as well. Files which cannot be read are skipped and reported as
+/// [Output::warnings].
+pub fn docs(
+    source: Source,
+    everest_core: Vec<SearchRoot>,
+    options: Options,
+    format: DocsFormat,
+) -> Result<Output> {
+    let templates = Templates::new(&options)?;
+    let extension = format.extension();
+    let mut yaml_repo = YamlRepo::new(everest_core);
+    let mut warnings = Vec::new();
+    let mut pages = BTreeMap::new();
+    let (mut interfaces, mut types, mut modules) = (Vec::new(), Vec::new(), Vec::new());
+
+    for name in yaml_repo.file_names("interfaces")? {
+        let path = format!("interfaces/{name}.{extension}");
+        let interface = match yaml_repo.get_interface(&name) {
+            Ok(interface) => interface,
+            Err(err) => {
+                warnings.push(format!("{err:#}"));
+                continue;
+            }
+        };
+        interfaces.push(Entry {
+            name: name.clone(),
+            href: path.clone(),
+            summary: summary(&interface.description),
+        });
+        pages.insert(
+            path.clone(),
+            interface_page(&name, interface, &path, extension),
+        );
+    }
+
+    for name in yaml_repo.file_names("types")? {
+        let path = format!("types/{name}.{extension}");
+        let data_types = match yaml_repo.get_data_types(&name) {
+            Ok(data_types) => data_types,
+            Err(err) => {
+                warnings.push(format!("{err:#}"));
+                continue;
+            }
+        };
+        let scope: Vec<_> = name.split('/').map(|s| s.to_string()).collect();
+        let describer = Describer {
+            page: &path,
+            extension,
+            scope: Some(&scope),
+        };
+        types.push(Entry {
+            name: name.clone(),
+            href: path.clone(),
+            summary: summary(&data_types.description),
+        });
+        let page = Page::Types {
+            name: name.clone(),
+            description: data_types.description.clone(),
+            types: data_types
+                .types
+                .iter()
+                .map(|(type_name, t)| describer.value(type_name, t))
+                .collect(),
+        };
+        pages.insert(path, page);
+    }
+
+    let mut manifests = BTreeMap::new();
+    for name in yaml_repo.file_names("modules")? {
+        let Some(module) = name.strip_suffix("/manifest") else {
+            continue;
+        };
+        match yaml_repo.get_manifest(module) {
+            Ok(manifest) => {
+                manifests.insert(module.to_string(), manifest.clone());
+            }
+            Err(err) => warnings.push(format!("{err:#}")),
+        }
+    }
+    if let Source::Manifest(path) = &source {
+        let manifest: Manifest = parse_yaml(path)?;
+        manifests.insert(module_name(path)?, manifest);
+    }
+    for (name, manifest) in &manifests {
+        let path = format!("modules/{name}.{extension}");
+        modules.push(Entry {
+            name: name.clone(),
+            href: path.clone(),
+            summary: summary(&manifest.description),
+        });
+        pages.insert(path.clone(), module_page(name, manifest, &path, extension));
+    }
+
+    pages.insert(
+        format!("index.{extension}"),
+        Page::Index {
+            interfaces,
+            types,
+            modules,
+        },
+    );
+
+    let env = &templates.env;
+    let template_name = format.template();
+    let template = env.get_template(template_name)?;
+    let mut files = BTreeMap::new();
+    for (path, page) in &pages {
+        let context = PageContext {
+            root: href(path, ""),
+            page,
+        };
+        let rendered = template.render(&context).with_context(|| {
+            format!("Failed to render the `{template_name}` template for {path}")
+        })?;
+        files.insert(PathBuf::from(path), rendered + "\n");
+    }
+
+    let mut input_files = yaml_repo.files().clone();
+    if let Source::Manifest(path) = &source {
+        input_files.insert(path.clone());
+    }
+    input_files.extend(templates.files);
+    warnings.extend(yaml_repo.warnings().iter().cloned());
+    Ok(Output {
+        files,
+        input_files,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{roots, tree};
+    use std::path::Path;
+
+    const SETTINGS_TYPES: &str = r#"
+description: Settings
+types:
+  Settings:
+    description: The settings
+    type: object
+    required: [step]
+    properties:
+      step:
+        type: integer
+        minimum: 1
+      mode:
+        type: string
+        $ref: /settings#/Mode
+        default: Fast
+  Mode:
+    description: How fast to count
+    type: string
+    enum: [Fast, Slow]
+"#;
+
+    const SETTINGS_INTERFACE: &str = r#"
+description: Counts by settings
+cmds:
+  add:
+    description: |
+      Adds to the step.
+
+      Returns the new settings.
+    arguments:
+      value:
+        description: The value to add
+        type: integer
+        default: 1
+    result:
+      description: The new settings
+      type: object
+      $ref: /settings#/Settings
+vars:
+  settings:
+    description: The current settings
+    type: object
+    $ref: /settings#/Settings
+    qos: 1
+"#;
+
+    #[test]
+    fn renders_the_pages() {
+        let dir = tree(&[
+            ("types/settings.yaml", SETTINGS_TYPES),
+            ("interfaces/counter.yaml", SETTINGS_INTERFACE),
+            ("interfaces/broken.yaml", "cmds: ["),
+        ]);
+        let out = docs(
+            Source::None,
+            roots(&dir),
+            Options::default(),
+            DocsFormat::Markdown,
+        )
+        .unwrap();
+        assert_eq!(
+            out.files.keys().collect::<Vec<_>>(),
+            ["index.md", "interfaces/counter.md", "types/settings.md"]
+        );
+        assert!(
+            out.warnings[0].contains("broken.yaml"),
+            "{:?}",
+            out.warnings
+        );
+
+        let page = |path: &str| &out.files[Path::new(path)];
+        for (path, parts) in [
+            (
+                "index.md",
+                &["- [counter](interfaces/counter.md): Counts by settings"][..],
+            ),
+            (
+                "interfaces/counter.md",
+                &[
+                    // The paragraphs of the description are kept.
+                    "Adds to the step.\n\nReturns the new settings.",
+                    "- `value` (integer, default: `1`, optional): The value to add",
+                    "Result: [`Settings`](../types/settings.md#Settings)",
+                    "Type: [`Settings`](../types/settings.md#Settings), QoS `1`",
+                ],
+            ),
+            (
+                "types/settings.md",
+                &[
+                    "<a id=\"Mode\"></a>",
+                    "- `mode` ([`Mode`](../types/settings.md#Mode), default: `\"Fast\"`, optional)",
+                    "- `step` (integer, minimum: `1`)",
+                ],
+            ),
+        ] {
+            let page = page(path);
+            for part in parts {
+                assert!(page.contains(part), "`{part}` not in {path}:\n{page}");
+            }
+        }
+
+        let out = docs(
+            Source::None,
+            roots(&dir),
+            Options::default(),
+            DocsFormat::Html,
+        )
+        .unwrap();
+        let page = &out.files[Path::new("interfaces/counter.html")];
+        assert!(page.contains("settings.html#Settings\""), "{page}");
+    }
+}
@@ -0,0 +1,447 @@
+//! Renders how an EVerest config wires up its modules as a graph.
+
+use crate::codegen::{parse_yaml, Options, Output, SearchRoot, Templates, YamlRepo};
+use crate::schema::{Config, Manifest};
+use crate::wiring::{self, ProblemKind};
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+/// The output format of [graph].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Graphviz DOT.
+    #[default]
+    Dot,
+    Mermaid,
+}
+
+impl std::str::FromStr for GraphFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "dot" => Ok(Self::Dot),
+            "mermaid" => Ok(Self::Mermaid),
+            _ => bail!("Unknown graph format `{s}`, expected `dot` or `mermaid`"),
+        }
+    }
+}
+
+impl GraphFormat {
+    fn template(self) -> &'static str {
+        match self {
+            Self::Dot => "graph_dot",
+            Self::Mermaid => "graph_mermaid",
+        }
+    }
+
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::Dot => "graph.dot",
+            Self::Mermaid => "graph.mmd",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ImplementationNode {
+    id: String,
+    interface: String,
+    node: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ModuleNode {
+    id: String,
+    module: String,
+    node: String,
+    /// Set if the manifest of the module could not be read.
+    problem: Option<String>,
+    implementations: Vec<ImplementationNode>,
+}
+
+/// A node which stands in for a connection target which does not exist or
+/// for a requirement without enough connections.
+#[derive(Debug, Serialize)]
+struct Placeholder {
+    node: String,
+    label: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Edge {
+    from: String,
+    to: String,
+    requirement_id: String,
+    interface: String,
+    problem: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct GraphContext {
+    modules: Vec<ModuleNode>,
+    placeholders: Vec<Placeholder>,
+    edges: Vec<Edge>,
+}
+
+/// Returns an identifier for a node which is valid in every format. Every
+/// character of the parts but ASCII letters and digits is escaped as `_<hex>_`,
+/// so that different parts never give the same identifier.
+fn node_id(kind: &str, parts: &[&str]) -> String {
+    let mut id = kind.to_string();
+    for part in parts {
+        id.push_str("__");
+        for c in part.chars() {
+            if c.is_ascii_alphanumeric() {
+                id.push(c);
+            } else {
+                id.push_str(&format!("_{:x}_", c as u32));
+            }
+        }
+    }
+    id
+}
+
+/// Builds the graph and reports every unresolved or over-subscribed
+/// requirement and every broken connection to `problems`.
+fn build(
+    config: &Config,
+    manifests: &BTreeMap<String, Manifest>,
+    problems: &mut Vec<String>,
+) -> GraphContext {
+    let mut graph = GraphContext::default();
+    for (id, module) in &config.active_modules {
+        let manifest = manifests.get(&module.module);
+        graph.modules.push(ModuleNode {
+            id: id.clone(),
+            module: module.module.clone(),
+            node: node_id("module", &[id]),
+            problem: manifest.is_none().then(|| "unknown module".to_string()),
+            implementations: manifest
+                .into_iter()
+                .flat_map(|m| &m.provides)
+                .map(|(implementation_id, provides)| ImplementationNode {
+                    id: implementation_id.clone(),
+                    interface: provides.interface.clone(),
+                    node: node_id("implementation", &[id, implementation_id]),
+                })
+                .collect(),
+        });
+    }
+
+    // The problems of the requirements and of the connections, by module id,
+    // requirement id and the index of the connection.
+    let mut requirement_problems = BTreeMap::new();
+    let mut connection_problems = BTreeMap::new();
+    for problem in wiring::check(config, manifests) {
+        let key = (problem.module_id, problem.requirement_id);
+        match problem.connection {
+            Some((i, _)) => connection_problems.insert((key, i), problem.kind),
+            None => requirement_problems.insert(key, problem.kind),
+        };
+    }
+
+    for (id, module) in &config.active_modules {
+        let Some(manifest) = manifests.get(&module.module) else {
+            continue;
+        };
+        let from = node_id("module", &[id]);
+        for (requirement_id, connections) in &module.connections {
+            let key = (id.as_str(), requirement_id.as_str());
+            let requirement_problem = requirement_problems.get(&key);
+            let interface = manifest
+                .requires
+                .get(requirement_id)
+                .map_or("?", |r| r.interface.as_str());
+            for (i, connection) in connections.iter().enumerate() {
+                let (module_id, implementation_id) =
+                    (&connection.module_id, &connection.implementation_id);
+                let mut to = node_id("implementation", &[module_id, implementation_id]);
+                let problem = match (requirement_problem, connection_problems.get(&(key, i))) {
+                    (Some(ProblemKind::UndeclaredRequirement), _) => {
+                        Some("undeclared requirement".to_string())
+                    }
+                    (_, Some(ProblemKind::MissingModule)) => {
+                        to = node_id("missing", &[module_id, implementation_id]);
+                        graph.placeholders.push(Placeholder {
+                            node: to.clone(),
+                            label: format!("missing module {module_id}"),
+                        });
+                        Some("unresolved".to_string())
+                    }
+                    (_, Some(ProblemKind::MissingImplementation)) => {
+                        to = node_id("missing", &[module_id, implementation_id]);
+                        graph.placeholders.push(Placeholder {
+                            node: to.clone(),
+                            label: format!(
+                                "missing implementation {module_id}.{implementation_id}"
+                            ),
+                        });
+                        Some("unresolved".to_string())
+                    }
+                    (_, Some(ProblemKind::WrongInterface { provided, .. })) => {
+                        Some(format!("provides {provided}"))
+                    }
+                    (Some(&ProblemKind::ConnectionCount { count, max, .. }), _) if count > max => {
+                        Some(format!("over-subscribed, {count} > {max}"))
+                    }
+                    _ => None,
+                };
+                // The module itself is flagged if it has no manifest.
+                let target = config.active_modules.get(module_id);
+                if target.is_some_and(|t| !manifests.contains_key(&t.module)) {
+                    to = node_id("module", &[module_id]);
+                }
+                if let Some(problem) = &problem {
+                    problems.push(format!(
+                        "{id}.{requirement_id} -> {module_id}.{implementation_id}: {problem}"
+                    ));
+                }
+                graph.edges.push(Edge {
+                    from: from.clone(),
+                    to,
+                    requirement_id: requirement_id.clone(),
+                    interface: interface.to_string(),
+                    problem,
+                });
+            }
+        }
+
+        for (requirement_id, requirement) in &manifest.requires {
+            let key = (id.as_str(), requirement_id.as_str());
+            let Some(&ProblemKind::ConnectionCount { count, min, .. }) =
+                requirement_problems.get(&key)
+            else {
+                continue;
+            };
+            if count >= min {
+                continue;
+            }
+            let problem = format!("unresolved, {count} < {min}");
+            problems.push(format!("{id}.{requirement_id}: {problem}"));
+            let to = node_id("unresolved", &[id, requirement_id]);
+            graph.placeholders.push(Placeholder {
+                node: to.clone(),
+                label: "unresolved".to_string(),
+            });
+            graph.edges.push(Edge {
+                from: from.clone(),
+                to,
+                requirement_id: requirement_id.clone(),
+                interface: requirement.interface.clone(),
+                problem: Some(problem),
+            });
+        }
+    }
+    graph
+}
+
+/// Renders the modules of the config at `config_path` with their
+/// implementations as nodes and the connections as edges labelled with the
+/// requirement and its interface. Unresolved and over-subscribed
+/// requirements are highlighted and returned as [Output::warnings]. The
+/// manifests are read from `modules/<module>/manifest.yaml` in the search
+/// roots.
+pub fn graph(
+    config_path: &Path,
+    everest_core: Vec<SearchRoot>,
+    options: Options,
+    format: GraphFormat,
+) -> Result<Output> {
+    let templates = Templates::new(&options)?;
+    let config: Config = parse_yaml(config_path)?;
+    let mut yaml_repo = YamlRepo::new(everest_core);
+    let mut warnings = Vec::new();
+    let mut manifests = BTreeMap::new();
+    let names: BTreeSet<_> = config.active_modules.values().map(|m| &m.module).collect();
+    for name in names {
+        match yaml_repo.get_manifest(name) {
+            Ok(manifest) => {
+                manifests.insert(name.clone(), manifest.clone());
+            }
+            Err(err) => warnings.push(format!("{err:#}")),
+        }
+    }
+
+    let context = build(&config, &manifests, &mut warnings);
+    let env = &templates.env;
+    let name = format.template();
+    let graph = env
+        .get_template(name)?
+        .render(&context)
+        .with_context(|| format!("Failed to render the `{name}` template"))?;
+
+    let mut input_files = yaml_repo.files().clone();
+    input_files.insert(config_path.to_path_buf());
+    input_files.extend(templates.files);
+    warnings.extend(yaml_repo.warnings().iter().cloned());
+    Ok(Output {
+        files: BTreeMap::from([(PathBuf::from(format.file_name()), graph + "\n")]),
+        input_files,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METER_MANIFEST: &str = r#"
+description: Measures
+provides:
+  main:
+    interface: meter
+    description: The meter
+metadata:
+  license: https://opensource.org/licenses/Apache-2.0
+  authors: [Jane Doe]
+"#;
+
+    const CHARGER_MANIFEST: &str = r#"
+description: Charges
+provides:
+  main:
+    interface: charger
+    description: The charger
+requires:
+  meter:
+    interface: meter
+  peers:
+    interface: charger
+    min_connections: 0
+    max_connections: 1
+metadata:
+  license: https://opensource.org/licenses/Apache-2.0
+  authors: [Jane Doe]
+"#;
+
+    /// Builds the graph of `config` with the modules `Meter` and `Charger`
+    /// and returns the edges as `from -> to: problem` and the problems.
+    fn edges(config: &str) -> (Vec<String>, Vec<String>) {
+        let config: Config = serde_yaml::from_str(config).unwrap();
+        let manifests = BTreeMap::from([
+            (
+                "Meter".to_string(),
+                serde_yaml::from_str(METER_MANIFEST).unwrap(),
+            ),
+            (
+                "Charger".to_string(),
+                serde_yaml::from_str(CHARGER_MANIFEST).unwrap(),
+            ),
+        ]);
+        let mut problems = Vec::new();
+        let graph = build(&config, &manifests, &mut problems);
+        let edges = graph
+            .edges
+            .iter()
+            .map(|e| {
+                let problem = e.problem.as_deref().unwrap_or("ok");
+                format!("{} -> {}: {problem}", e.from, e.to)
+            })
+            .collect();
+        (edges, problems)
+    }
+
+    #[test]
+    fn resolved_edges() {
+        let (edges, problems) = edges(
+            r#"
+active_modules:
+  charger:
+    module: Charger
+    connections:
+      meter:
+        - module_id: meter
+          implementation_id: main
+  meter:
+    module: Meter
+"#,
+        );
+        assert_eq!(
+            edges,
+            ["module__charger -> implementation__meter__main: ok"]
+        );
+        assert!(problems.is_empty(), "{problems:?}");
+    }
+
+    #[test]
+    fn edge_problems() {
+        let (edges, problems) = edges(
+            r#"
+active_modules:
+  charger:
+    module: Charger
+    connections:
+      meter:
+        - module_id: meter
+          implementation_id: main
+        - module_id: meter
+          implementation_id: backup
+      peers:
+        - module_id: meter
+          implementation_id: main
+      display:
+        - module_id: meter
+          implementation_id: main
+  lonely:
+    module: Charger
+    connections:
+      meter:
+        - module_id: gone
+          implementation_id: main
+      peers:
+        - module_id: ghost
+          implementation_id: main
+  idle:
+    module: Charger
+  ghost:
+    module: Unknown
+  meter:
+    module: Meter
+"#,
+        );
+        assert_eq!(
+            edges,
+            [
+                "module__charger -> implementation__meter__main: undeclared requirement",
+                "module__charger -> implementation__meter__main: over-subscribed, 2 > 1",
+                "module__charger -> missing__meter__backup: unresolved",
+                "module__charger -> implementation__meter__main: provides meter",
+                "module__idle -> unresolved__idle__meter: unresolved, 0 < 1",
+                "module__lonely -> missing__gone__main: unresolved",
+                "module__lonely -> module__ghost: ok",
+            ]
+        );
+        assert_eq!(
+            problems,
+            [
+                "charger.display -> meter.main: undeclared requirement",
+                "charger.meter -> meter.main: over-subscribed, 2 > 1",
+                "charger.meter -> meter.backup: unresolved",
+                "charger.peers -> meter.main: provides meter",
+                "idle.meter: unresolved, 0 < 1",
+                "lonely.meter -> gone.main: unresolved",
+            ]
+        );
+    }
+
+    #[test]
+    fn node_ids_are_injective() {
+        let ids = [
+            node_id("module", &["a-b"]),
+            node_id("module", &["a_b"]),
+            node_id("module", &["a.b"]),
+            node_id("module", &["a__b"]),
+            node_id("module", &["a", "b"]),
+            node_id("module", &["a_", "b"]),
+            node_id("module", &["a", "_b"]),
+        ];
+        let unique: BTreeSet<_> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len(), "{ids:?}");
+        for id in &ids {
+            assert!(id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+        }
+    }
+}
@@ -1,17 +1,39 @@
+mod asyncapi;
 mod codegen;
+mod docs;
+mod graph;
+mod lint;
+pub mod model;
 pub mod schema;
+#[cfg(test)]
+mod testing;
+mod wiring;
 
-use anyhow::{Context, Result};
-use std::io::Write;
-use std::path::PathBuf;
-use std::process::Command;
+pub use codegen::{Options, Output, SearchRoot, Source};
+pub use docs::DocsFormat;
+pub use graph::GraphFormat;
+pub use lint::Diagnostic;
+
+use anyhow::{bail, Context, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where an installed EVerest keeps its `interfaces`, `types` and `errors`,
+/// relative to the installation prefix.
+const SHARE_DIR: &str = "share/everest";
+
+/// The name of the crate written by [Builder::generate_types_crate] if no
+/// [Builder::types_crate] is given.
+const DEFAULT_TYPES_CRATE: &str = "everest_types";
 
 #[derive(Debug, Default)]
 pub struct Builder {
-    everest_core: Vec<PathBuf>,
-    // TODO(hrapp): This is almost always the same anyways.
-    manifest_path: PathBuf,
+    /// The search roots, in order of precedence.
+    everest_core: Vec<SearchRoot>,
+    source: Source,
     out_dir: Option<PathBuf>,
+    options: Options,
 }
 
 impl Builder {
@@ -19,32 +41,540 @@ impl Builder {
         Self {
             everest_core: everest_core
                 .into_iter()
-                .map(|element| element.into())
+                .map(|element| SearchRoot {
+                    path: element.into(),
+                    namespace: None,
+                })
                 .collect::<Vec<_>>(),
-            manifest_path: manifest_path.into(),
+            source: Source::Manifest(manifest_path.into()),
+            ..Builder::default()
+        }
+    }
+
+    /// Creates a builder which generates only the service and client traits,
+    /// dispatchers and publishers of the `interfaces` and the types they use.
+    /// There is no manifest and no `Module`, the callbacks get the publisher
+    /// of their own interface. Add the search roots with
+    /// [Builder::search_root] or [Builder::prefix].
+    pub fn for_interfaces(interfaces: &[impl AsRef<str>]) -> Self {
+        Self {
+            source: Source::Interfaces(
+                interfaces
+                    .iter()
+                    .map(|name| name.as_ref().to_string())
+                    .collect(),
+            ),
             ..Builder::default()
         }
     }
 
+    /// Creates a builder which takes interfaces and types from an installed
+    /// EVerest under `prefix` instead of from source checkouts. The manifest
+    /// defaults to the `manifest.yaml` next to the `Cargo.toml` of the crate
+    /// being built.
+    pub fn from_prefix(prefix: impl Into<PathBuf>) -> Self {
+        let manifest_dir = std::env::var_os("CARGO_MANIFEST_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        Self {
+            source: Source::Manifest(manifest_dir.join("manifest.yaml")),
+            ..Builder::default()
+        }
+        .prefix(prefix)
+    }
+
+    pub fn manifest(mut self, path: impl Into<PathBuf>) -> Self {
+        self.source = Source::Manifest(path.into());
+        self
+    }
+
+    /// Adds the data directory of an EVerest installed under `prefix` as a
+    /// search root, see [Builder::search_root].
+    pub fn prefix(self, prefix: impl Into<PathBuf>) -> Self {
+        self.search_root(prefix.into().join(SHARE_DIR))
+    }
+
     pub fn out_dir(mut self, path: impl Into<PathBuf>) -> Self {
         self.out_dir = Some(path.into());
         self
     }
 
-    pub fn generate(self) -> Result<()> {
-        let path = self
+    /// Adds a search root with lower precedence than all roots added before.
+    /// Interfaces and types are taken from the first root defining them, so
+    /// a vendor root added first can override files from everest-core.
+    pub fn search_root(mut self, path: impl Into<PathBuf>) -> Self {
+        self.everest_core.push(SearchRoot {
+            path: path.into(),
+            namespace: None,
+        });
+        self
+    }
+
+    /// Like [Builder::search_root], but the root only provides names inside
+    /// `namespace`. For example with the namespace `acme`, the type reference
+    /// `/acme/powermeter#/Foo` is looked up in `<path>/types/powermeter.yaml`.
+    pub fn namespaced_root(
+        mut self,
+        namespace: impl Into<String>,
+        path: impl Into<PathBuf>,
+    ) -> Self {
+        self.everest_core.push(SearchRoot {
+            path: path.into(),
+            namespace: Some(namespace.into()),
+        });
+        self
+    }
+
+    /// Generates one `<Interface><Command>Args` struct per command which is
+    /// passed instead of positional arguments. Adding an argument to an
+    /// interface then no longer reorders the parameters of existing methods.
+    pub fn command_args_structs(mut self, enable: bool) -> Self {
+        self.options.command_args_structs = enable;
+        self
+    }
+
+    /// Maps schema integers to the narrowest Rust integer type which covers
+    /// their `minimum` and `maximum`, e.g. `u8` for `0..=255`. Values outside
+    /// of the schema bounds are rejected during deserialization.
+    pub fn precise_integers(mut self, enable: bool) -> Self {
+        self.options.precise_integers = enable;
+        self
+    }
+
+    /// Writes `generated.rs` as root of a module tree with one file per types
+    /// module (`generated/types/<ns>.rs`), one per interface
+    /// (`generated/interfaces/<name>.rs`) and the module glue
+    /// (`generated/module.rs`). This keeps the files for modules with many
+    /// interfaces manageable.
+    pub fn split_modules(mut self, enable: bool) -> Self {
+        self.options.split_modules = enable;
+        self
+    }
+
+    /// Derives `schemars::JsonSchema` for all generated types and argument
+    /// structs. The bounds, lengths, patterns, formats and `enum`s of the
+    /// schema go along as `#[schemars(...)]` attributes, so the derived
+    /// schema matches the YAML definition. The crate needs a dependency on
+    /// `schemars` 1.x. A [Builder::types_crate] must have been generated with
+    /// the same setting.
+    pub fn json_schema(mut self, enable: bool) -> Self {
+        self.options.json_schema = enable;
+        self
+    }
+
+    /// Implements `proptest::arbitrary::Arbitrary` for all generated types and
+    /// argument structs, for property tests of handlers and serde round
+    /// trips. The values respect the bounds, lengths, patterns, `enum`s and
+    /// `minItems`/`maxItems` of the schema and leave out optional properties
+    /// at random. Recursive types are cut off after a few levels. The crate
+    /// needs a dependency on `proptest` 1.x. A [Builder::types_crate] must
+    /// have been generated with the same setting.
+    pub fn arbitrary(mut self, enable: bool) -> Self {
+        self.options.arbitrary = enable;
+        self
+    }
+
+    /// Renders with the `<name>.jinja2` templates in `dir` instead of the
+    /// built-in ones of the same name. Templates missing in `dir` fall back
+    /// to the built-in ones, see `jinja/README.md` for the names and the
+    /// context they are rendered with.
+    pub fn template_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.options.template_dir = Some(dir.into());
+        self
+    }
+
+    /// Takes the types from the crate `name` generated by
+    /// [Builder::generate_types_crate] instead of generating them into the
+    /// module, which `generated::types` then re-exports. The crate must have
+    /// been generated with the same [Builder::precise_integers].
+    pub fn types_crate(mut self, name: impl Into<String>) -> Self {
+        self.options.types_crate = Some(name.into());
+        self
+    }
+
+    /// Sets the visibility of the generated items, `pub(crate)` by default.
+    /// With `pub` the bindings can live in a library crate which several
+    /// binaries and tests share. See also [Builder::root_module].
+    pub fn visibility(mut self, visibility: impl Into<String>) -> Self {
+        self.options.visibility = visibility.into();
+        self
+    }
+
+    /// Sets the absolute path of the `generated` module, `crate::generated`
+    /// by default. If the `generated.rs` is included into `crate::foo`, this
+    /// is `crate::foo::generated`. The generated code refers to the types by
+    /// this path.
+    pub fn root_module(mut self, path: impl Into<String>) -> Self {
+        self.options.root_module = path.into();
+        self
+    }
+
+    /// Returns every file the generated code depends on: The manifest and all
+    /// interface and types files reachable from it. This is useful for build
+    /// systems other than cargo.
+    pub fn input_files(&self) -> Result<Vec<PathBuf>> {
+        let input_files = codegen::input_files(
+            self.source.clone(),
+            self.everest_core.clone(),
+            self.options.clone(),
+        )?;
+        Ok(input_files.into_iter().collect())
+    }
+
+    /// Resolves the manifest and everything it references into the
+    /// interfaces and types the code is generated from, without generating
+    /// any code.
+    pub fn resolve(&self) -> Result<model::Model> {
+        model::resolve(
+            self.source.clone(),
+            self.everest_core.clone(),
+            self.options.clone(),
+        )
+    }
+
+    /// Checks the manifest and every interface and types file it references
+    /// against the schemas of the framework. Also reports unknown
+    /// interfaces, config keys which are no valid identifiers, `$ref`s to
+    /// types which do not exist and `min_connections > max_connections`.
+    /// Without a manifest, all interfaces and types of the search roots are
+    /// checked. Errors only mean that the search roots could not be read.
+    pub fn lint(&self) -> Result<Vec<Diagnostic>> {
+        lint::lint(self.source.clone(), self.everest_core.clone())
+    }
+
+    /// Checks the EVerest config file at `path` against the manifests of
+    /// its modules, which are looked up in `modules/<module>` of the search
+    /// roots. Reports connections to missing modules or implementations or
+    /// to the wrong interface, connection counts outside of
+    /// `min_connections` and `max_connections` and undeclared config keys.
+    pub fn lint_config(&self, path: impl AsRef<Path>) -> Vec<Diagnostic> {
+        lint::lint_config(path.as_ref(), self.everest_core.clone())
+    }
+
+    /// Renders how the EVerest config at `path` wires up its modules as
+    /// graph, see [GraphFormat]. The manifests are looked up in
+    /// `modules/<module>` of the search roots. Unresolved and
+    /// over-subscribed requirements are highlighted and returned as
+    /// [Output::warnings].
+    pub fn graph(&self, path: impl AsRef<Path>, format: GraphFormat) -> Result<Output> {
+        graph::graph(
+            path.as_ref(),
+            self.everest_core.clone(),
+            self.options.clone(),
+            format,
+        )
+    }
+
+    /// Writes one page per interface, types file and module manifest of the
+    /// search roots and an index into [Builder::out_dir], see [DocsFormat].
+    /// The pages list the commands with their arguments, results and
+    /// constraints, the variables with their QoS and the errors, and link
+    /// every `$ref` to the page of its type. The manifest is documented as
+    /// well if one is given. Files which cannot be read are skipped with a
+    /// warning.
+    pub fn generate_docs(self, format: DocsFormat) -> Result<()> {
+        let out_dir = self
             .out_dir
-            .unwrap_or_else(|| PathBuf::from(std::env::var("OUT_DIR").unwrap()))
-            .join("generated.rs");
+            .context("Generating the docs needs an out_dir")?;
+        let out = docs::docs(self.source, self.everest_core, self.options, format)?;
+        for warning in &out.warnings {
+            eprintln!("warning: {warning}");
+        }
+        write_files(&out_dir, &out.files)
+    }
 
-        let out = codegen::emit(self.manifest_path, self.everest_core)?;
+    /// Writes the API the manifest provides as AsyncAPI document
+    /// `asyncapi.json` and every type it uses as JSON Schema
+    /// `schemas/<types file>/<Type>.json` into [Builder::out_dir]. Commands
+    /// are request/reply operations and variables send operations on the
+    /// MQTT topics of the framework.
+    pub fn generate_asyncapi(self) -> Result<()> {
+        let out_dir = self
+            .out_dir
+            .context("Exporting the AsyncAPI document needs an out_dir")?;
+        let out = asyncapi::asyncapi(self.source, self.everest_core)?;
+        for warning in &out.warnings {
+            eprintln!("warning: {warning}");
+        }
+        write_files(&out_dir, &out.files)
+    }
+
+    /// Writes a [cargo-fuzz](https://github.com/rust-fuzz/cargo-fuzz) crate
+    /// for the manifest into [Builder::out_dir], usually the `fuzz`
+    /// directory of the module. It has one target per provided
+    /// implementation with commands and per requirement with variables,
+    /// which feeds the input as JSON into `dispatch_command_to_*` resp.
+    /// `dispatch_variable_to_*` with a stub subscriber, so that malformed
+    /// messages which crash the decoding are found. The crate must be a
+    /// member of the workspace, which also provides a [Builder::types_crate].
+    /// The [Builder::root_module] must be the default. All files are
+    /// overwritten, run `cargo fuzz run <implementation_id>_commands` to
+    /// fuzz.
+    pub fn generate_fuzz_targets(self) -> Result<()> {
+        let out_dir = self
+            .out_dir
+            .context("Generating the fuzz targets needs an out_dir")?;
+        let Source::Manifest(manifest_path) = self.source else {
+            bail!("Generating the fuzz targets needs a manifest");
+        };
+        fs::create_dir_all(&out_dir)
+            .with_context(|| format!("Could not create {}", out_dir.display()))?;
+        let out_dir = out_dir.canonicalize()?;
 
-        let mut f = std::fs::File::create(&path).context("Could not generate the output file.")?;
-        f.write_all(out.as_bytes())?;
+        let out =
+            codegen::emit_fuzz_targets(&manifest_path, self.everest_core, self.options, &out_dir)?;
+        for warning in &out.warnings {
+            eprintln!("warning: {warning}");
+        }
+        write_files(&out_dir, &out.files)
+    }
+
+    /// Generates the code without writing it, e.g. for a procedural macro.
+    /// Only works for a single file, see [Builder::split_modules].
+    pub fn emit(&self) -> Result<Output> {
+        if self.options.split_modules {
+            bail!("A module tree can only be generated into an out_dir");
+        }
+        codegen::emit(
+            self.source.clone(),
+            self.everest_core.clone(),
+            self.options.clone(),
+            Path::new(""),
+        )
+    }
+
+    /// Generates the code. If no [Builder::out_dir] is given, we assume that
+    /// we run in a build script, write to `OUT_DIR` and tell cargo about all
+    /// inputs.
+    pub fn generate(self) -> Result<()> {
+        let in_build_script = self.out_dir.is_none();
+        let out_dir = match self.out_dir {
+            Some(out_dir) => out_dir,
+            None => PathBuf::from(
+                std::env::var("OUT_DIR").context("No out_dir given and OUT_DIR is not set")?,
+            ),
+        };
+        fs::create_dir_all(&out_dir)
+            .with_context(|| format!("Could not create {}", out_dir.display()))?;
+        let out_dir = out_dir.canonicalize()?;
+
+        let out = codegen::emit(
+            self.source,
+            self.everest_core.clone(),
+            self.options.clone(),
+            &out_dir,
+        )?;
+
+        if in_build_script {
+            print_rerun_if_changed(&out.input_files, &self.everest_core, &self.options);
+        }
+        for warning in &out.warnings {
+            if in_build_script {
+                println!("cargo:warning={warning}");
+            } else {
+                eprintln!("warning: {warning}");
+            }
+        }
+
+        // Drop files of interfaces which are no longer used.
+        let tree = out_dir.join(codegen::MODULE_TREE_DIR);
+        if tree.is_dir() {
+            fs::remove_dir_all(&tree)
+                .with_context(|| format!("Could not remove {}", tree.display()))?;
+        }
+        write_files(&out_dir, &out.files)
+    }
+
+    /// Generates a crate with every type of the search roots into
+    /// [Builder::out_dir], so that several modules can share the types. The
+    /// crate is named after [Builder::types_crate] or `everest_types`. Its
+    /// `Cargo.toml` is only written if it does not exist yet, so that the
+    /// dependencies can be adjusted. With [Builder::precise_integers] it
+    /// takes `everestrs` from the `[workspace.dependencies]`. The manifest is
+    /// not used.
+    pub fn generate_types_crate(self) -> Result<()> {
+        let out_dir = self
+            .out_dir
+            .context("Generating the types crate needs an out_dir")?;
+        let name = self
+            .options
+            .types_crate
+            .clone()
+            .unwrap_or_else(|| DEFAULT_TYPES_CRATE.to_string());
+        let options = Options {
+            types_crate: Some(name.clone()),
+            ..self.options
+        };
+
+        let out = codegen::emit_types_crate(self.everest_core, options.clone())?;
+        for warning in &out.warnings {
+            eprintln!("warning: {warning}");
+        }
+        write_files(&out_dir, &out.files)?;
 
-        if let Err(_) = Command::new("rustfmt").args(path.to_str()).output() {
-            println!("Failed to format code");
+        let cargo_toml = out_dir.join("Cargo.toml");
+        if !cargo_toml.exists() {
+            fs::write(&cargo_toml, types_crate_manifest(&name, &options))
+                .with_context(|| format!("Could not write {}", cargo_toml.display()))?;
         }
         Ok(())
     }
+
+    /// Writes a new module called `module_name` into [Builder::out_dir]: A
+    /// `manifest.yaml` with the `provides` and `requires` given as pairs of
+    /// implementation id and interface and the `authors`, a `Cargo.toml`, a
+    /// `build.rs` which generates the code with the search roots and options
+    /// of this builder and a `src/main.rs` with stubs for all callbacks.
+    /// Without `authors`, the manifest lists a placeholder. Existing files
+    /// are never overwritten. The manifest is not used. A
+    /// [Builder::types_crate] is taken from the `[workspace.dependencies]`,
+    /// the [Builder::root_module] must be the default.
+    pub fn scaffold(
+        self,
+        module_name: &str,
+        provides: &[(impl AsRef<str>, impl AsRef<str>)],
+        requires: &[(impl AsRef<str>, impl AsRef<str>)],
+        authors: &[impl AsRef<str>],
+    ) -> Result<()> {
+        let out_dir = self
+            .out_dir
+            .context("Scaffolding a module needs an out_dir")?;
+        fs::create_dir_all(&out_dir)
+            .with_context(|| format!("Could not create {}", out_dir.display()))?;
+        let out_dir = out_dir.canonicalize()?;
+
+        let out = codegen::emit_scaffold(
+            module_name,
+            slots(provides),
+            slots(requires),
+            authors.iter().map(|a| a.as_ref().to_string()).collect(),
+            self.everest_core,
+            self.options,
+            &out_dir,
+        )?;
+        for warning in &out.warnings {
+            eprintln!("warning: {warning}");
+        }
+        if let Some(path) = out.files.keys().find(|path| out_dir.join(path).exists()) {
+            bail!("Refusing to overwrite {}", out_dir.join(path).display());
+        }
+        write_files(&out_dir, &out.files)
+    }
+}
+
+/// Turns pairs of implementation id and interface into slots.
+fn slots(entries: &[(impl AsRef<str>, impl AsRef<str>)]) -> Vec<codegen::SlotContext> {
+    entries
+        .iter()
+        .map(|(id, interface)| codegen::SlotContext {
+            implementation_id: id.as_ref().to_string(),
+            interface: interface.as_ref().to_string(),
+        })
+        .collect()
+}
+
+/// Writes the `files` relative to `out_dir`.
+fn write_files(out_dir: &Path, files: &BTreeMap<PathBuf, String>) -> Result<()> {
+    for (path, code) in files {
+        let path = out_dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, code).with_context(|| format!("Could not write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Returns the `Cargo.toml` of the types crate.
+fn types_crate_manifest(name: &str, options: &Options) -> String {
+    let mut manifest = format!(
+        r#"[package]
+name = "{}"
+version = "0.1.0"
+edition = "2021"
+
+[dependencies]
+serde = {{ version = "1.0", features = ["derive"] }}
+serde_json = "1.0"
+"#,
+        name.replace('_', "-")
+    );
+    // The bounded integers are deserialized by the runtime.
+    if options.precise_integers {
+        manifest.push_str("everestrs = { workspace = true }\n");
+    }
+    if options.json_schema {
+        manifest.push_str("schemars = \"1\"\n");
+    }
+    if options.arbitrary {
+        manifest.push_str("proptest = \"1\"\n");
+    }
+    manifest
+}
+
+/// Tells cargo to rerun the build script if any of the `input_files` change.
+///
+/// We also watch the `interfaces` and `types` directories of every root, so
+/// that a file added to a root which shadows another one is noticed as well.
+/// Watching the roots themselves would rerun the build script all the time
+/// since they usually contain build outputs. The template directory is
+/// watched for added templates.
+fn print_rerun_if_changed(
+    input_files: &BTreeSet<PathBuf>,
+    everest_core: &[SearchRoot],
+    options: &Options,
+) {
+    let directories = everest_core
+        .iter()
+        .flat_map(|root| [root.path.join("interfaces"), root.path.join("types")])
+        .chain(options.template_dir.clone())
+        .filter(|dir| dir.is_dir());
+    for path in input_files.iter().cloned().chain(directories) {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::tree;
+
+    const PING_INTERFACE: &str = "
+description: Pings
+cmds:
+  ping:
+    description: Pings back
+";
+
+    const PING_MANIFEST: &str = "
+description: Pings
+provides:
+  main:
+    interface: ping
+    description: The ping
+metadata:
+  license: https://opensource.org/licenses/Apache-2.0
+  authors: [Jane Doe]
+";
+
+    #[test]
+    fn reads_an_installed_prefix() {
+        let prefix = tree(&[
+            ("share/everest/interfaces/ping.yaml", PING_INTERFACE),
+            ("share/everest/modules/RsPing/manifest.yaml", PING_MANIFEST),
+        ]);
+        let share = prefix.path().join(SHARE_DIR);
+        let manifest = share.join("modules/RsPing/manifest.yaml");
+        for builder in [
+            Builder::from_prefix(prefix.path()).manifest(&manifest),
+            Builder::new(&manifest, Vec::<PathBuf>::new()).prefix(prefix.path()),
+            Builder::for_interfaces(&["ping"]).prefix(prefix.path()),
+        ] {
+            let model = builder.resolve().unwrap();
+            assert_eq!(model.interfaces[0].commands[0].name, "ping");
+            assert!(model
+                .input_files
+                .contains(&share.join("interfaces/ping.yaml")));
+        }
+    }
 }
@@ -0,0 +1,874 @@
+//! Checks manifests, interfaces, types and config files against the schemas
+//! of the framework and for problems the schemas cannot express, without
+//! needing the framework at runtime.
+
+use crate::codegen::{parse_yaml, SearchRoot, Source, TypeRef, YamlRepo};
+use crate::schema::manifest::ConfigEntry;
+use crate::schema::Config;
+use crate::wiring::{self, ProblemKind};
+use anyhow::Result;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+// Copies of the schemas of the framework, so that the crate can be packaged
+// on its own. Keep them in sync with `schemas/` at the root of the repository.
+const MANIFEST_SCHEMA: &str = include_str!("../schemas/manifest.yaml");
+const INTERFACE_SCHEMA: &str = include_str!("../schemas/interface.yaml");
+const TYPE_SCHEMA: &str = include_str!("../schemas/type.yaml");
+const CONFIG_SCHEMA: &str = include_str!("../schemas/config.yaml");
+
+/// The keywords of Rust, which cannot be used as field names.
+const RUST_KEYWORDS: [&str; 51] = [
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while",
+];
+
+/// A problem found by [lint].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    pub file: PathBuf,
+    /// The JSON pointer of the offending value in the file, e.g.
+    /// `/requires/powermeter`, or empty for the whole file.
+    pub location: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.location.as_str() {
+            "" => write!(f, "{}: {}", self.file.display(), self.message),
+            location => write!(f, "{}: {location}: {}", self.file.display(), self.message),
+        }
+    }
+}
+
+/// A schema of the framework, see [Schema::source].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Schema {
+    Manifest,
+    Interface,
+    Type,
+    Config,
+}
+
+impl Schema {
+    fn source(self) -> &'static str {
+        match self {
+            Self::Manifest => MANIFEST_SCHEMA,
+            Self::Interface => INTERFACE_SCHEMA,
+            Self::Type => TYPE_SCHEMA,
+            Self::Config => CONFIG_SCHEMA,
+        }
+    }
+
+    /// Compiles the schema. The draft-07 meta-schema which `type.yaml`
+    /// references is built into the validator, so nothing is fetched.
+    fn validator(self) -> jsonschema::Validator {
+        // The schemas are compiled in, so they always parse.
+        let schema: Value = serde_yaml::from_str(self.source()).unwrap();
+        jsonschema::draft7::options().build(&schema).unwrap()
+    }
+}
+
+/// A file to lint.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Item {
+    Manifest(PathBuf),
+    Interface(String),
+    Types(String),
+}
+
+struct Linter {
+    yaml_repo: YamlRepo,
+    queue: VecDeque<Item>,
+    seen: BTreeSet<Item>,
+    /// The types files by name, `None` if they could not be read.
+    types: BTreeMap<String, Option<Value>>,
+    validators: BTreeMap<Schema, jsonschema::Validator>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter {
+    fn new(everest_core: Vec<SearchRoot>) -> Self {
+        Self {
+            yaml_repo: YamlRepo::new(everest_core),
+            queue: VecDeque::new(),
+            seen: BTreeSet::new(),
+            types: BTreeMap::new(),
+            validators: BTreeMap::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, item: Item) {
+        if self.seen.insert(item.clone()) {
+            self.queue.push_back(item);
+        }
+    }
+
+    fn report(&mut self, file: &Path, location: impl Into<String>, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic {
+            file: file.to_path_buf(),
+            location: location.into(),
+            message: message.into(),
+        });
+    }
+
+    /// Reads `path` and checks it against `schema`.
+    fn load(&mut self, path: &Path, schema: Schema) -> Option<Value> {
+        let value = match parse_yaml::<Value>(path) {
+            Ok(value) => value,
+            Err(err) => {
+                self.report(path, "", format!("{err:#}"));
+                return None;
+            }
+        };
+        let violations: Vec<_> = self
+            .validators
+            .entry(schema)
+            .or_insert_with(|| schema.validator())
+            .iter_errors(&value)
+            .map(|err| (err.instance_path.to_string(), err.to_string()))
+            .collect();
+        for (location, message) in violations {
+            self.report(path, location, message);
+        }
+        Some(value)
+    }
+
+    fn lint_manifest(&mut self, path: &Path) {
+        let Some(manifest) = self.load(path, Schema::Manifest) else {
+            return;
+        };
+
+        let config_sets = std::iter::once(("/config".to_string(), manifest.get("config"))).chain(
+            entries(&manifest, "provides").map(|(id, entry)| {
+                (
+                    format!("/provides/{}/config", pointer_escape(id)),
+                    entry.get("config"),
+                )
+            }),
+        );
+        let mut invalid_keys = Vec::new();
+        for (location, config) in config_sets {
+            for key in config
+                .and_then(Value::as_object)
+                .into_iter()
+                .flatten()
+                .map(|(k, _)| k)
+            {
+                if let Err(message) = check_identifier(key) {
+                    invalid_keys.push((format!("{location}/{}", pointer_escape(key)), message));
+                }
+            }
+        }
+        for (location, message) in invalid_keys {
+            self.report(path, location, message);
+        }
+
+        for section in ["provides", "requires"] {
+            let slots: Vec<_> = entries(&manifest, section)
+                .map(|(id, entry)| (id.clone(), entry.clone()))
+                .collect();
+            for (id, entry) in slots {
+                let location = format!("/{section}/{}", pointer_escape(&id));
+                if let Some(interface) = entry.get("interface").and_then(Value::as_str) {
+                    match self.yaml_repo.find("interfaces", interface) {
+                        Some(_) => self.push(Item::Interface(interface.to_string())),
+                        None => self.report(
+                            path,
+                            format!("{location}/interface"),
+                            format!("Unknown interface `{interface}`"),
+                        ),
+                    }
+                }
+                let count = |key| entry.get(key).and_then(Value::as_i64);
+                // Both default to 1.
+                let (min, max) = (
+                    count("min_connections").unwrap_or(1),
+                    count("max_connections").unwrap_or(1),
+                );
+                if min > max {
+                    self.report(
+                        path,
+                        location,
+                        format!("min_connections {min} is greater than max_connections {max}"),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Checks that the modules of the config exist and are wired up the way
+    /// their manifests declare.
+    fn lint_config(&mut self, path: &Path) {
+        let reported = self.diagnostics.len();
+        let Some(value) = self.load(path, Schema::Config) else {
+            return;
+        };
+        let config: Config = match serde_json::from_value(value) {
+            Ok(config) => config,
+            Err(err) => {
+                // Values the schema rejects are reported already.
+                if self.diagnostics.len() == reported {
+                    self.report(path, "", format!("{err:#}"));
+                }
+                return;
+            }
+        };
+
+        // The manifests by module name, for the modules which have one.
+        let mut manifests = BTreeMap::new();
+        for (id, module) in &config.active_modules {
+            match self.yaml_repo.get_manifest(&module.module) {
+                Ok(manifest) => {
+                    manifests.insert(module.module.clone(), manifest.clone());
+                }
+                Err(err) => self.report(
+                    path,
+                    format!("/active_modules/{}/module", pointer_escape(id)),
+                    format!("{err:#}"),
+                ),
+            }
+        }
+
+        for (id, module) in &config.active_modules {
+            let Some(manifest) = manifests.get(&module.module) else {
+                continue;
+            };
+            let location = format!("/active_modules/{}", pointer_escape(id));
+            self.check_config_keys(
+                path,
+                &format!("{location}/config_module"),
+                &module.config_module,
+                &manifest.config,
+                "the module",
+            );
+            for (implementation_id, config) in &module.config_implementation {
+                let location = format!(
+                    "{location}/config_implementation/{}",
+                    pointer_escape(implementation_id)
+                );
+                match manifest.provides.get(implementation_id) {
+                    Some(provides) => self.check_config_keys(
+                        path,
+                        &location,
+                        config,
+                        &provides.config,
+                        &format!("the implementation `{implementation_id}`"),
+                    ),
+                    None => self.report(
+                        path,
+                        location,
+                        format!("The module has no implementation `{implementation_id}`"),
+                    ),
+                }
+            }
+        }
+
+        for problem in wiring::check(&config, &manifests) {
+            let (location, message) = wiring_diagnostic(&problem);
+            self.report(path, location, message);
+        }
+    }
+
+    /// Reports the keys of `config` which are not `declared`.
+    fn check_config_keys(
+        &mut self,
+        path: &Path,
+        location: &str,
+        config: &BTreeMap<String, Value>,
+        declared: &BTreeMap<String, ConfigEntry>,
+        owner: &str,
+    ) {
+        for key in config.keys() {
+            if !declared.contains_key(key) {
+                self.report(
+                    path,
+                    format!("{location}/{}", pointer_escape(key)),
+                    format!("`{key}` is not declared in the config of {owner}"),
+                );
+            }
+        }
+    }
+
+    /// Returns the diagnostics ordered by file and location.
+    fn finish(mut self) -> Vec<Diagnostic> {
+        self.diagnostics
+            .sort_by(|a, b| (&a.file, &a.location).cmp(&(&b.file, &b.location)));
+        self.diagnostics
+    }
+
+    fn lint_interface(&mut self, name: &str) {
+        let Some(path) = self.yaml_repo.find("interfaces", name) else {
+            return;
+        };
+        if let Some(interface) = self.load(&path, Schema::Interface) {
+            self.check_refs(&path, &interface, None);
+        }
+    }
+
+    fn lint_types(&mut self, name: &str) {
+        let Some(path) = self.yaml_repo.find("types", name) else {
+            return;
+        };
+        let value = self.load(&path, Schema::Type);
+        if let Some(value) = &value {
+            let scope: Vec<_> = name.split('/').map(|s| s.to_string()).collect();
+            self.check_refs(&path, value, Some(&scope));
+        }
+        self.types.insert(name.to_string(), value);
+    }
+
+    /// Reports every `$ref` in `value` which does not point to a type.
+    fn check_refs(&mut self, path: &Path, value: &Value, scope: Option<&[String]>) {
+        let mut refs = Vec::new();
+        collect_refs(value, "", &mut refs);
+        for (location, reference) in refs {
+            let type_ref = match TypeRef::from_reference(&reference, scope) {
+                Ok(type_ref) => type_ref,
+                Err(err) => {
+                    self.report(path, location, format!("{err:#}"));
+                    continue;
+                }
+            };
+            let name = type_ref.module_path.join("/");
+            if !self.types.contains_key(&name) {
+                let Some(types_path) = self.yaml_repo.find("types", &name) else {
+                    let message =
+                        format!("Dangling $ref `{reference}`: There is no types file `{name}`");
+                    self.report(path, location, message);
+                    continue;
+                };
+                self.push(Item::Types(name.clone()));
+                // Read it without diagnostics, they are reported when the
+                // file itself is linted.
+                self.types
+                    .insert(name.clone(), parse_yaml::<Value>(&types_path).ok());
+            }
+            // Unreadable types files are reported on their own.
+            if let Some(Some(types)) = self.types.get(&name) {
+                if !has_type(types, &type_ref.type_name) {
+                    let message = format!(
+                        "Dangling $ref `{reference}`: `{name}` has no type `{}`",
+                        type_ref.type_name
+                    );
+                    self.report(path, location, message);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the location in the config and the message for `problem`.
+fn wiring_diagnostic(problem: &wiring::Problem) -> (String, String) {
+    let requirement_id = problem.requirement_id;
+    let mut location = format!(
+        "/active_modules/{}/connections",
+        pointer_escape(problem.module_id)
+    );
+    if !matches!(problem.kind, ProblemKind::ConnectionCount { .. }) {
+        location = format!("{location}/{}", pointer_escape(requirement_id));
+    }
+    if let Some((i, _)) = problem.connection {
+        location = format!("{location}/{i}");
+    }
+    let (module_id, implementation_id) = problem.connection.map_or(("", ""), |(_, c)| {
+        (c.module_id.as_str(), c.implementation_id.as_str())
+    });
+    let message = match problem.kind {
+        ProblemKind::UndeclaredRequirement => {
+            format!("The module has no requirement `{requirement_id}`")
+        }
+        ProblemKind::MissingModule => format!("There is no module `{module_id}`"),
+        ProblemKind::MissingImplementation => {
+            format!("Module `{module_id}` has no implementation `{implementation_id}`")
+        }
+        ProblemKind::WrongInterface { provided, required } => format!(
+            "`{module_id}.{implementation_id}` implements `{provided}`, but `{requirement_id}` \
+             requires `{required}`"
+        ),
+        ProblemKind::ConnectionCount { count, min, max } => {
+            let expected = if min == max {
+                format!("{min}")
+            } else {
+                format!("between {min} and {max}")
+            };
+            format!("Requirement `{requirement_id}` has {count} connections, but needs {expected}")
+        }
+    };
+    (location, message)
+}
+
+/// Escapes `key` for use in a JSON pointer.
+fn pointer_escape(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Returns the entries of the object `section` of `value`.
+fn entries<'a>(value: &'a Value, section: &str) -> impl Iterator<Item = (&'a String, &'a Value)> {
+    value
+        .get(section)
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+}
+
+fn has_type(types: &Value, name: &str) -> bool {
+    types
+        .get("types")
+        .and_then(Value::as_object)
+        .is_some_and(|types| types.contains_key(name))
+}
+
+/// Collects the values of all `$ref` keys in `value` with their location.
+fn collect_refs(value: &Value, pointer: &str, refs: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(object) => {
+            for (key, child) in object {
+                let child_pointer = format!("{pointer}/{}", pointer_escape(key));
+                match (key.as_str(), child) {
+                    ("$ref", Value::String(reference)) => {
+                        refs.push((child_pointer, reference.clone()))
+                    }
+                    _ => collect_refs(child, &child_pointer, refs),
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                collect_refs(item, &format!("{pointer}/{i}"), refs);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Config keys become field names in the generated code.
+fn check_identifier(key: &str) -> std::result::Result<(), String> {
+    let mut chars = key.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(format!("Config key `{key}` is not a valid identifier"));
+    }
+    if RUST_KEYWORDS.contains(&key) {
+        return Err(format!("Config key `{key}` is a Rust keyword"));
+    }
+    Ok(())
+}
+
+/// Lints the `source` and every interface and types file it references. For
+/// [Source::None], all interfaces and types files in the search roots are
+/// linted instead. Problems with the files are returned,
+/// errors only mean that the search roots could not be read.
+pub fn lint(source: Source, everest_core: Vec<SearchRoot>) -> Result<Vec<Diagnostic>> {
+    let mut linter = Linter::new(everest_core);
+    match source {
+        Source::None => {
+            for name in linter.yaml_repo.file_names("interfaces")? {
+                linter.push(Item::Interface(name));
+            }
+            for name in linter.yaml_repo.file_names("types")? {
+                linter.push(Item::Types(name));
+            }
+        }
+        Source::Manifest(path) => linter.push(Item::Manifest(path)),
+        Source::Interfaces(names) => {
+            for name in names {
+                match linter.yaml_repo.find("interfaces", &name) {
+                    Some(_) => linter.push(Item::Interface(name)),
+                    None => {
+                        let message = format!("Unknown interface `{name}`");
+                        linter.report(Path::new(&name), "", message);
+                    }
+                }
+            }
+        }
+    }
+
+    while let Some(item) = linter.queue.pop_front() {
+        match item {
+            Item::Manifest(path) => linter.lint_manifest(&path),
+            Item::Interface(name) => linter.lint_interface(&name),
+            Item::Types(name) => linter.lint_types(&name),
+        }
+    }
+    Ok(linter.finish())
+}
+
+/// Checks the EVerest config at `path` against the schema and the manifests
+/// of its modules: Every connection must point to an implementation of the
+/// required interface of an active module, the number of connections must
+/// fit the manifest and all config keys must be declared there. The
+/// manifests are read from `modules/<module>/manifest.yaml` in the search
+/// roots.
+pub fn lint_config(path: &Path, everest_core: Vec<SearchRoot>) -> Vec<Diagnostic> {
+    let mut linter = Linter::new(everest_core);
+    linter.lint_config(path);
+    linter.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{roots, tree};
+
+    const INTERFACE: &str = r#"
+description: Counts
+cmds:
+  add:
+    description: Adds to the counter
+    arguments:
+      value:
+        description: The value to add
+        type: integer
+      step:
+        description: The step of the counter
+        type: object
+        $ref: /counter#/Step
+vars:
+  count:
+    description: The current count
+    type: integer
+"#;
+
+    const TYPES: &str = r#"
+description: Types of the counter
+types:
+  Step:
+    description: A step
+    type: object
+    additionalProperties: false
+    properties:
+      size:
+        type: integer
+        minimum: 1
+"#;
+
+    const MANIFEST: &str = r#"
+description: Counts
+provides:
+  main:
+    interface: counter
+    description: The counter
+metadata:
+  license: https://opensource.org/licenses/Apache-2.0
+  authors:
+    - Jane Doe
+"#;
+
+    /// Lints `source` in a search root with the counter interface, types and
+    /// manifest, where `files` replace or add to them.
+    fn lint_tree(source: Source, files: &[(&str, &str)]) -> Vec<(String, String, String)> {
+        let mut all = vec![
+            ("interfaces/counter.yaml", INTERFACE),
+            ("types/counter.yaml", TYPES),
+            ("modules/RsCounter/manifest.yaml", MANIFEST),
+        ];
+        for (path, content) in files {
+            all.retain(|(p, _)| p != path);
+            all.push((path, content));
+        }
+        let dir = tree(&all);
+        let source = match source {
+            Source::Manifest(path) => Source::Manifest(dir.path().join(path)),
+            source => source,
+        };
+        lint(source, roots(&dir))
+            .unwrap()
+            .into_iter()
+            .map(|d| {
+                let file = d.file.strip_prefix(dir.path()).unwrap_or(&d.file);
+                (file.display().to_string(), d.location, d.message)
+            })
+            .collect()
+    }
+
+    const USER_MANIFEST: &str = r#"
+description: Uses counters
+config:
+  limit:
+    description: The limit
+    type: integer
+provides:
+  main:
+    interface: idle
+    description: Nothing
+requires:
+  counters:
+    interface: counter
+    min_connections: 1
+    max_connections: 2
+metadata:
+  license: https://opensource.org/licenses/Apache-2.0
+  authors:
+    - Jane Doe
+"#;
+
+    /// Lints the `config` against the counter files and a module `RsUser`
+    /// which provides an `idle` implementation and requires counters.
+    fn lint_config_tree(config: &str) -> Vec<(String, String)> {
+        let dir = tree(&[
+            ("interfaces/counter.yaml", INTERFACE),
+            ("types/counter.yaml", TYPES),
+            ("modules/RsCounter/manifest.yaml", MANIFEST),
+            ("interfaces/idle.yaml", "description: Does nothing\n"),
+            ("modules/RsUser/manifest.yaml", USER_MANIFEST),
+            ("config.yaml", config),
+        ]);
+        lint_config(&dir.path().join("config.yaml"), roots(&dir))
+            .into_iter()
+            .map(|d| (d.location, d.message))
+            .collect()
+    }
+
+    fn manifest() -> Source {
+        Source::Manifest("modules/RsCounter/manifest.yaml".into())
+    }
+
+    #[test]
+    fn valid_files_lint_clean() {
+        assert_eq!(lint_tree(Source::None, &[]), []);
+        assert_eq!(lint_tree(manifest(), &[]), []);
+        let interfaces = Source::Interfaces(vec!["counter".to_string()]);
+        assert_eq!(lint_tree(interfaces, &[]), []);
+    }
+
+    #[test]
+    fn invalid_manifest() {
+        let manifest_yaml = MANIFEST.replace("    - Jane Doe\n", "    - 42\n");
+        let diagnostics = lint_tree(
+            manifest(),
+            &[("modules/RsCounter/manifest.yaml", &manifest_yaml)],
+        );
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        let (file, location, _) = &diagnostics[0];
+        assert_eq!(file, "modules/RsCounter/manifest.yaml");
+        assert_eq!(location, "/metadata/authors/0");
+    }
+
+    #[test]
+    fn manifest_checks() {
+        let manifest_yaml = r#"
+description: Counts
+provides:
+  main:
+    interface: counter
+    description: The counter
+    config:
+      type:
+        description: The type of the counter
+        type: integer
+requires:
+  other:
+    interface: meter
+    min_connections: 2
+    max_connections: 1
+metadata:
+  license: https://opensource.org/licenses/Apache-2.0
+  authors:
+    - Jane Doe
+"#;
+        let diagnostics = lint_tree(
+            manifest(),
+            &[("modules/RsCounter/manifest.yaml", manifest_yaml)],
+        );
+        let diagnostics: Vec<_> = diagnostics.into_iter().map(|(_, l, m)| (l, m)).collect();
+        assert_eq!(
+            diagnostics,
+            [
+                (
+                    "/provides/main/config/type".to_string(),
+                    "Config key `type` is a Rust keyword".to_string()
+                ),
+                (
+                    "/requires/other".to_string(),
+                    "min_connections 2 is greater than max_connections 1".to_string()
+                ),
+                (
+                    "/requires/other/interface".to_string(),
+                    "Unknown interface `meter`".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_interface() {
+        let interface = INTERFACE.replace("    description: Adds", "    summary: Adds");
+        let diagnostics = lint_tree(Source::None, &[("interfaces/counter.yaml", &interface)]);
+        assert_eq!(diagnostics.len(), 2, "{diagnostics:?}");
+        for (file, location, _) in &diagnostics {
+            assert_eq!(file, "interfaces/counter.yaml");
+            assert_eq!(location, "/cmds/add");
+        }
+    }
+
+    #[test]
+    fn invalid_types() {
+        // `minimum` of an integer must be a number per the draft-07
+        // meta-schema, which `type.yaml` references.
+        let types = TYPES.replace("minimum: 1", "minimum: one");
+        let diagnostics = lint_tree(Source::None, &[("types/counter.yaml", &types)]);
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        let (file, location, _) = &diagnostics[0];
+        assert_eq!(file, "types/counter.yaml");
+        assert_eq!(location, "/types/Step/properties/size/minimum");
+    }
+
+    #[test]
+    fn dangling_refs() {
+        let interface = INTERFACE.replace("/counter#/Step", "/counter#/Stride");
+        let diagnostics = lint_tree(manifest(), &[("interfaces/counter.yaml", &interface)]);
+        assert_eq!(
+            diagnostics,
+            [(
+                "interfaces/counter.yaml".to_string(),
+                "/cmds/add/arguments/step/$ref".to_string(),
+                "Dangling $ref `/counter#/Stride`: `counter` has no type `Stride`".to_string(),
+            )]
+        );
+
+        let interface = INTERFACE.replace("/counter#/Step", "/stepper#/Step");
+        let diagnostics = lint_tree(manifest(), &[("interfaces/counter.yaml", &interface)]);
+        assert_eq!(diagnostics.len(), 1, "{diagnostics:?}");
+        assert!(diagnostics[0]
+            .2
+            .contains("There is no types file `stepper`"));
+    }
+
+    #[test]
+    fn valid_config_lints_clean() {
+        let config = r#"
+active_modules:
+  user:
+    module: RsUser
+    config_module:
+      limit: 3
+    telemetry:
+      id: 1
+    connections:
+      counters:
+        - module_id: counter
+          implementation_id: main
+  counter:
+    module: RsCounter
+"#;
+        assert_eq!(lint_config_tree(config), []);
+    }
+
+    #[test]
+    fn config_diagnostics_are_sorted() {
+        let config = r#"
+active_modules:
+  user:
+    module: RsUser
+    config_module:
+      limits: 3
+    config_implementation:
+      backup:
+        step: 1
+    connections:
+      counters:
+        - module_id: counter
+          implementation_id: other
+        - module_id: meter
+          implementation_id: main
+        - module_id: other_user
+          implementation_id: main
+  counter:
+    module: RsCounter
+  other_user:
+    module: RsUser
+    connections:
+      counter:
+        - module_id: counter
+          implementation_id: main
+  broken:
+    module: RsMissing
+"#;
+        let diagnostics = lint_config_tree(config);
+        let locations: Vec<_> = diagnostics.iter().map(|(l, _)| l.as_str()).collect();
+        assert_eq!(
+            locations,
+            [
+                "/active_modules/broken/module",
+                "/active_modules/other_user/connections",
+                "/active_modules/other_user/connections/counter",
+                "/active_modules/user/config_implementation/backup",
+                "/active_modules/user/config_module/limits",
+                "/active_modules/user/connections",
+                "/active_modules/user/connections/counters/0",
+                "/active_modules/user/connections/counters/1",
+                "/active_modules/user/connections/counters/2",
+            ],
+            "{diagnostics:#?}"
+        );
+        let messages: Vec<_> = diagnostics.iter().map(|(_, m)| m.as_str()).collect();
+        assert_eq!(
+            messages[1..],
+            [
+                "Requirement `counters` has 0 connections, but needs between 1 and 2",
+                "The module has no requirement `counter`",
+                "The module has no implementation `backup`",
+                "`limits` is not declared in the config of the module",
+                "Requirement `counters` has 3 connections, but needs between 1 and 2",
+                "Module `counter` has no implementation `other`",
+                "There is no module `meter`",
+                "`other_user.main` implements `idle`, but `counters` requires `counter`",
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_interface() {
+        let diagnostics = lint_tree(Source::Interfaces(vec!["meter".to_string()]), &[]);
+        assert_eq!(
+            diagnostics,
+            [(
+                "meter".to_string(),
+                String::new(),
+                "Unknown interface `meter`".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn schemas_compile() {
+        for schema in [
+            Schema::Manifest,
+            Schema::Interface,
+            Schema::Type,
+            Schema::Config,
+        ] {
+            schema.validator();
+        }
+    }
+
+    #[test]
+    fn vendored_schemas_match_the_framework() {
+        let framework = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../schemas");
+        // Outside of the repository, e.g. in a packaged crate, there is
+        // nothing to compare against.
+        if !framework.exists() {
+            return;
+        }
+        for (name, vendored) in [
+            ("manifest.yaml", MANIFEST_SCHEMA),
+            ("interface.yaml", INTERFACE_SCHEMA),
+            ("type.yaml", TYPE_SCHEMA),
+            ("config.yaml", CONFIG_SCHEMA),
+        ] {
+            let original = std::fs::read_to_string(framework.join(name)).unwrap();
+            assert_eq!(vendored, original, "schemas/{name} is out of date");
+        }
+    }
+}
@@ -0,0 +1,446 @@
+//! The resolved module, as the code is generated from it.
+//!
+//! [Builder::resolve](crate::Builder::resolve) returns the module with all
+//! interfaces and all types they use, directly or transitively, which is a
+//! good starting point for generators of other outputs like documentation or
+//! test fixtures. Every `$ref` is resolved to a [TypeRef] into
+//! [Model::types]. The types are given as the Rust types of the generated
+//! code, for other languages the [Field::type_refs] tell which named types a
+//! field uses.
+
+use crate::codegen::{
+    self, ArgumentContext, CommandContext, InterfaceContext, Options, Resolved, SearchRoot,
+    SlotContext, Source, TypeContext, YamlRepo,
+};
+use crate::schema::Manifest;
+use anyhow::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+pub use crate::codegen::TypeRef;
+
+/// The result of [Builder::resolve](crate::Builder::resolve).
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Model {
+    /// The module, `None` for
+    /// [Builder::for_interfaces](crate::Builder::for_interfaces).
+    pub module: Option<Module>,
+    /// The provided and required interfaces, without duplicates.
+    pub interfaces: Vec<Interface>,
+    /// All types used by the interfaces, directly or transitively.
+    pub types: BTreeMap<TypeRef, NamedType>,
+    /// The manifest and every interface and types file the model depends on.
+    pub input_files: BTreeSet<PathBuf>,
+    /// Problems which did not prevent the resolution.
+    pub warnings: Vec<String>,
+}
+
+/// The module the manifest describes.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Module {
+    /// The manifest as read, e.g. for its description and metadata.
+    pub manifest: Manifest,
+    /// The implementations the module provides.
+    pub provides: Vec<Slot>,
+    /// The implementations the module requires.
+    pub requires: Vec<Slot>,
+    /// The config of the module itself.
+    pub config: Vec<Field>,
+    /// The config of the provided implementations by implementation id.
+    pub implementation_config: BTreeMap<String, Vec<Field>>,
+    /// The additional properties of the provided implementations by
+    /// implementation id.
+    pub properties: BTreeMap<String, Vec<Property>>,
+}
+
+/// An entry of `provides` or `requires` in the manifest.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Slot {
+    pub implementation_id: String,
+    /// The name of the interface, see [Interface::name].
+    pub interface: String,
+}
+
+/// An interface with its commands and variables.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Interface {
+    /// The file name under `interfaces/`, without `.yaml`.
+    pub name: String,
+    pub description: String,
+    pub commands: Vec<Command>,
+    pub variables: Vec<Field>,
+    /// Whether the module provides resp. requires this interface.
+    pub provided: bool,
+    pub required: bool,
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Command {
+    pub name: String,
+    pub description: String,
+    pub arguments: Vec<Field>,
+    /// The return value, always named `return_value`.
+    pub result: Option<Field>,
+}
+
+/// An argument, result, variable, property of an object or config entry.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Field {
+    /// The name as in the YAML.
+    pub name: String,
+    pub description: Option<String>,
+    /// The Rust type, e.g. `Vec<crate::generated::types::foo::Bar>`.
+    pub rust_type: String,
+    /// The named types `rust_type` refers to, all of which are in
+    /// [Model::types].
+    pub type_refs: BTreeSet<TypeRef>,
+    /// The schema `default` as Rust expression.
+    pub default: Option<String>,
+}
+
+/// An additional property of a provided implementation.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct Property {
+    /// The name as in the YAML.
+    pub name: String,
+    /// The name of the constant in the generated code.
+    pub constant: String,
+    /// The Rust type of the `value`.
+    pub rust_type: String,
+    /// The value as Rust expression.
+    pub value: String,
+}
+
+/// The target of a `$ref`.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum NamedType {
+    Object {
+        properties: Vec<Field>,
+    },
+    /// A string enum with its variants, which are also their serialized
+    /// names.
+    Enum {
+        items: Vec<String>,
+    },
+}
+
+impl From<&ArgumentContext> for Field {
+    fn from(arg: &ArgumentContext) -> Self {
+        Field {
+            name: arg.name.clone(),
+            description: arg.description.clone(),
+            rust_type: arg.data_type.name.clone(),
+            type_refs: arg.data_type.type_refs.clone(),
+            default: arg.default.clone(),
+        }
+    }
+}
+
+impl From<&CommandContext> for Command {
+    fn from(cmd: &CommandContext) -> Self {
+        Command {
+            name: cmd.name.clone(),
+            description: cmd.description.clone(),
+            arguments: fields(&cmd.arguments),
+            result: cmd.result.as_ref().map(Field::from),
+        }
+    }
+}
+
+impl From<&InterfaceContext> for Interface {
+    fn from(interface: &InterfaceContext) -> Self {
+        Interface {
+            name: interface.name.clone(),
+            description: interface.description.clone(),
+            commands: interface.cmds.iter().map(Command::from).collect(),
+            variables: fields(&interface.vars),
+            provided: interface.provided,
+            required: interface.required,
+        }
+    }
+}
+
+impl From<&SlotContext> for Slot {
+    fn from(slot: &SlotContext) -> Self {
+        Slot {
+            implementation_id: slot.implementation_id.clone(),
+            interface: slot.interface.clone(),
+        }
+    }
+}
+
+impl From<&TypeContext> for NamedType {
+    fn from(t: &TypeContext) -> Self {
+        match t {
+            TypeContext::Object(object) => NamedType::Object {
+                properties: fields(&object.properties),
+            },
+            TypeContext::Enum(e) => NamedType::Enum {
+                items: e.items.clone(),
+            },
+        }
+    }
+}
+
+fn fields(args: &[ArgumentContext]) -> Vec<Field> {
+    args.iter().map(Field::from).collect()
+}
+
+/// Reads the `source` and resolves all interfaces and types it references.
+pub(crate) fn resolve(
+    source: Source,
+    everest_core: Vec<SearchRoot>,
+    options: Options,
+) -> Result<Model> {
+    let Resolved {
+        context,
+        manifest,
+        input_files,
+        warnings,
+    } = codegen::resolve(source, &mut YamlRepo::new(everest_core), options)?;
+
+    let module = manifest.map(|manifest| Module {
+        manifest,
+        provides: context.provides.iter().map(Slot::from).collect(),
+        requires: context.requires.iter().map(Slot::from).collect(),
+        config: fields(&context.module_config),
+        implementation_config: context
+            .provided_config
+            .iter()
+            .map(|c| (c.name.clone(), fields(&c.config)))
+            .collect(),
+        properties: context
+            .provided_properties
+            .iter()
+            .map(|p| {
+                let properties = p
+                    .properties
+                    .iter()
+                    .map(|p| Property {
+                        name: p.name.clone(),
+                        constant: p.constant.clone(),
+                        rust_type: p.data_type.clone(),
+                        value: p.value.clone(),
+                    })
+                    .collect();
+                (p.name.clone(), properties)
+            })
+            .collect(),
+    });
+    Ok(Model {
+        module,
+        interfaces: context.interfaces.iter().map(Interface::from).collect(),
+        types: context
+            .named_types
+            .iter()
+            .map(|(r, t)| (r.clone(), NamedType::from(t)))
+            .collect(),
+        input_files,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{roots, tree};
+
+    const METER_TYPES: &str = r#"
+description: Measurements
+types:
+  Reading:
+    description: A reading
+    type: object
+    required: [unit]
+    properties:
+      unit:
+        type: string
+        $ref: /meter#/Unit
+      samples:
+        type: array
+        items:
+          type: number
+  Unit:
+    description: The unit
+    type: string
+    enum: [Wh, kWh]
+"#;
+
+    const METER_INTERFACE: &str = r#"
+description: Measures
+cmds:
+  history:
+    description: Returns the last readings
+    arguments:
+      count:
+        description: How many
+        type: integer
+        default: 10
+    result:
+      description: The readings
+      type: array
+      items:
+        type: object
+        $ref: /meter#/Reading
+vars:
+  reading:
+    description: The current reading
+    type: object
+    $ref: /meter#/Reading
+"#;
+
+    const METER_MANIFEST: &str = r#"
+description: Measures
+provides:
+  main:
+    interface: meter
+    description: The meter
+    phases: 3
+    config:
+      interval:
+        description: Seconds between readings
+        type: number
+requires:
+  peer:
+    interface: meter
+config:
+  verbose:
+    type: boolean
+metadata:
+  license: https://opensource.org/licenses/Apache-2.0
+  authors:
+    - Jane Doe
+"#;
+
+    fn reading() -> TypeRef {
+        TypeRef {
+            module_path: vec!["meter".to_string()],
+            type_name: "Reading".to_string(),
+        }
+    }
+
+    fn unit() -> TypeRef {
+        TypeRef {
+            module_path: vec!["meter".to_string()],
+            type_name: "Unit".to_string(),
+        }
+    }
+
+    #[test]
+    fn resolves_the_module() {
+        let dir = tree(&[
+            ("types/meter.yaml", METER_TYPES),
+            ("interfaces/meter.yaml", METER_INTERFACE),
+            ("modules/RsMeter/manifest.yaml", METER_MANIFEST),
+        ]);
+        let manifest = dir.path().join("modules/RsMeter/manifest.yaml");
+        let model = resolve(
+            Source::Manifest(manifest.clone()),
+            roots(&dir),
+            Options::default(),
+        )
+        .unwrap();
+
+        let [interface] = &model.interfaces[..] else {
+            panic!("{:?}", model.interfaces);
+        };
+        assert_eq!(interface.name, "meter");
+        assert!(interface.provided && interface.required);
+        let [history] = &interface.commands[..] else {
+            panic!("{:?}", interface.commands);
+        };
+        let [count] = &history.arguments[..] else {
+            panic!("{:?}", history.arguments);
+        };
+        assert_eq!(count.rust_type, "i64");
+        assert_eq!(count.default.as_deref(), Some("10"));
+        assert!(count.type_refs.is_empty());
+        let result = history.result.as_ref().unwrap();
+        assert_eq!(
+            result.rust_type,
+            "Vec<crate::generated::types::meter::Reading>"
+        );
+        assert_eq!(result.type_refs, BTreeSet::from([reading()]));
+        let [variable] = &interface.variables[..] else {
+            panic!("{:?}", interface.variables);
+        };
+        assert_eq!(variable.type_refs, BTreeSet::from([reading()]));
+
+        // `Unit` is only referenced by `Reading`.
+        assert_eq!(
+            model.types.keys().cloned().collect::<Vec<_>>(),
+            [reading(), unit()]
+        );
+        let NamedType::Object { properties } = &model.types[&reading()] else {
+            panic!("{:?}", model.types);
+        };
+        let fields: Vec<_> = properties
+            .iter()
+            .map(|p| (p.name.as_str(), p.rust_type.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                ("samples", "Option<Vec<f64>>"),
+                ("unit", "crate::generated::types::meter::Unit"),
+            ]
+        );
+        let NamedType::Enum { items } = &model.types[&unit()] else {
+            panic!("{:?}", model.types);
+        };
+        assert_eq!(items, &["Wh", "kWh"]);
+
+        let module = model.module.unwrap();
+        assert_eq!(module.manifest.description, "Measures");
+        let slots: Vec<_> = module
+            .provides
+            .iter()
+            .chain(&module.requires)
+            .map(|s| (s.implementation_id.as_str(), s.interface.as_str()))
+            .collect();
+        assert_eq!(slots, [("main", "meter"), ("peer", "meter")]);
+        assert_eq!(module.config[0].name, "verbose");
+        assert_eq!(module.config[0].rust_type, "bool");
+        assert_eq!(module.implementation_config["main"][0].rust_type, "f64");
+        let phases = &module.properties["main"][0];
+        assert_eq!(
+            (phases.constant.as_str(), phases.value.as_str()),
+            ("PHASES", "3")
+        );
+
+        for file in ["types/meter.yaml", "interfaces/meter.yaml"] {
+            assert!(model.input_files.contains(&dir.path().join(file)));
+        }
+        assert!(model.input_files.contains(&manifest));
+    }
+
+    #[test]
+    fn resolves_interfaces_without_module() {
+        let dir = tree(&[
+            ("types/meter.yaml", METER_TYPES),
+            ("interfaces/meter.yaml", METER_INTERFACE),
+        ]);
+        let options = Options {
+            types_crate: Some("meter_types".to_string()),
+            ..Options::default()
+        };
+        let model = resolve(
+            Source::Interfaces(vec!["meter".to_string()]),
+            roots(&dir),
+            options,
+        )
+        .unwrap();
+        assert!(model.module.is_none());
+        let variable = &model.interfaces[0].variables[0];
+        assert_eq!(variable.rust_type, "::meter_types::meter::Reading");
+        assert_eq!(model.types.len(), 2);
+    }
+}
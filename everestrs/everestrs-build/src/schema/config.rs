@@ -0,0 +1,38 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// The EVerest config file, see `schemas/config.yaml`. The `settings` are
+/// not parsed.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub active_modules: BTreeMap<String, ActiveModule>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ActiveModule {
+    /// The name of the module, i.e. its directory under `modules`.
+    pub module: String,
+    #[serde(default)]
+    pub config_module: BTreeMap<String, serde_json::Value>,
+    #[serde(default)]
+    pub config_implementation: BTreeMap<String, BTreeMap<String, serde_json::Value>>,
+    pub telemetry: Option<Telemetry>,
+    /// The connections by requirement id.
+    #[serde(default)]
+    pub connections: BTreeMap<String, Vec<Connection>>,
+}
+
+/// The schema allows further properties besides the `id`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Telemetry {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Connection {
+    pub module_id: String,
+    pub implementation_id: String,
+}
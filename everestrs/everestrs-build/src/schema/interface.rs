@@ -3,18 +3,28 @@ use std::collections::BTreeMap;
 
 use super::types::Type;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Interface {
     pub description: String,
     #[serde(default)]
     pub cmds: BTreeMap<String, Command>,
     #[serde(default)]
     pub vars: BTreeMap<String, Type>,
+    #[serde(default)]
+    pub errors: Vec<ErrorReference>,
     // TODO(ddo) We allow unknown fields for now to ignore the `error` interface
     // which is still under construction.
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// An error or a list of errors the interface may raise, either
+/// `/errors/<list>` or `/errors/<list>#/<Error>`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ErrorReference {
+    pub reference: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct Command {
     pub description: String,
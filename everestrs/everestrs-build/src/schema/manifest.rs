@@ -1,8 +1,8 @@
 use super::types::{BooleanOptions, IntegerOptions, NumberOptions, StringOptions};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Manifest {
     pub description: String,
@@ -12,10 +12,9 @@ pub struct Manifest {
     pub requires: BTreeMap<String, RequiresEntry>,
     #[serde(default)]
     pub enable_telemetry: bool,
-    // This is just here, so that we do not crash for deny_unknown_fields,
-    // this is never used in Rust code.
-    #[allow(dead_code)]
-    enable_external_mqtt: bool,
+    /// Rust modules ignore this and should use an MQTT client directly.
+    #[serde(default)]
+    pub enable_external_mqtt: bool,
 
     #[serde(default)]
     pub config: BTreeMap<String, ConfigEntry>,
@@ -27,16 +26,31 @@ pub struct Manifest {
     pub enable_global_errors: bool,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(deny_unknown_fields)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct ProvidesEntry {
     pub interface: String,
     pub description: String,
     #[serde(default)]
     pub config: BTreeMap<String, ConfigEntry>,
+    /// Arbitrary additional properties, which requirements can be matched
+    /// against.
+    #[serde(flatten)]
+    pub properties: BTreeMap<String, PropertyValue>,
 }
 
-#[derive(Debug, Deserialize)]
+/// The value of an additional property of a [ProvidesEntry]. The schema only
+/// allows primitive types here.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum PropertyValue {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Number(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct RequiresEntry {
     pub interface: String,
@@ -44,11 +58,14 @@ pub struct RequiresEntry {
     pub max_connections: Option<i64>,
 }
 
-#[derive(Debug, Deserialize)]
-#[serde(deny_unknown_fields)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct Metadata {
     pub license: String,
     pub authors: Vec<String>,
+    pub base_license: Option<String>,
+    /// The schema allows any additional metadata.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
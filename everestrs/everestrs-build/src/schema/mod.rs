@@ -1,7 +1,9 @@
+pub mod config;
 pub mod interface;
 pub mod manifest;
 pub mod types;
 
+pub use config::Config;
 pub use interface::Interface;
 pub use manifest::Manifest;
 pub use types::Type;
@@ -3,7 +3,7 @@ use std::collections::{BTreeMap, HashSet};
 
 /// Implements the schema defined under `type.yaml`. Every type has a `type`
 /// and a `description` field.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Type {
     // TODO(ddo) The schema says that this field is required, but multiple
     // type definitions do not obey this rule.
@@ -16,9 +16,22 @@ pub struct Type {
     pub qos: Option<i64>,
 }
 
+impl Type {
+    /// Whether the type has a `default`, so that the value may be omitted.
+    pub fn has_default(&self) -> bool {
+        match &self.arg {
+            TypeBase::Single(TypeEnum::Boolean(args)) => args.default.is_some(),
+            TypeBase::Single(TypeEnum::String(args)) => args.default.is_some(),
+            TypeBase::Single(TypeEnum::Number(args)) => args.default.is_some(),
+            TypeBase::Single(TypeEnum::Integer(args)) => args.default.is_some(),
+            _ => false,
+        }
+    }
+}
+
 /// The type may be either represented by a string or by an array of strings.
 /// In the case of an array of strings.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub enum TypeBase {
     Single(TypeEnum),
     Multiple(Vec<TypeEnum>),
@@ -46,7 +59,7 @@ impl<'de> Deserialize<'de> for TypeBase {
             }
             serde_yaml::Value::Sequence(s) => {
                 let mut types = Vec::with_capacity(s.len());
-                for t in s.into_iter() {
+                for t in s {
                     let mut mapping = serde_yaml::Mapping::new();
                     mapping.insert(serde_yaml::Value::String("type".to_string()), t.clone());
                     let t: TypeEnum = serde_yaml::from_value(serde_yaml::Value::Mapping(mapping))
@@ -88,7 +101,7 @@ pub struct IntegerOptions {
     pub default: Option<i64>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct ArrayOptions {
     pub min_items: Option<usize>,
@@ -96,7 +109,7 @@ pub struct ArrayOptions {
     pub items: Option<Box<Type>>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct ObjectOptions {
     #[serde(default)]
@@ -135,7 +148,7 @@ pub struct StringOptions {
     pub object_reference: Option<String>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase", tag = "type", deny_unknown_fields)]
 pub enum TypeEnum {
     Null,
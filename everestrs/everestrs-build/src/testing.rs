@@ -0,0 +1,167 @@
+//! Helpers for the tests, which describe their search roots inline.
+
+use crate::codegen::{Options, Output, SearchRoot};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Writes the `files`, given as path relative to the root and content, into
+/// a new temporary directory.
+pub(crate) fn tree(files: &[(&str, &str)]) -> TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    for (path, content) in files {
+        let path = dir.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+    dir
+}
+
+/// Returns `dir` as the only search root.
+pub(crate) fn roots(dir: &TempDir) -> Vec<SearchRoot> {
+    vec![SearchRoot {
+        path: dir.path().to_path_buf(),
+        namespace: None,
+    }]
+}
+
+/// The definitions of the C++ side of the bridge which a detached runtime
+/// needs. Everything else of the C++ side is left out, so the binaries link
+/// as long as they do not connect to EVerest.
+const DETACHED_RUNTIME: &str = r#"
+#[export_name = "cxxbridge1$unique_ptr$Module$null"]
+extern "C" fn unique_ptr_module_null(_: *mut ::std::ffi::c_void) {}
+#[export_name = "cxxbridge1$unique_ptr$Module$drop"]
+extern "C" fn unique_ptr_module_drop(_: *mut ::std::ffi::c_void) {}
+"#;
+
+/// A cargo workspace in a temporary directory, whose members take
+/// `everestrs` and `everestrs-build` from the `[workspace.dependencies]`
+/// like the modules of everest-core. The runtime is built without its C++
+/// part, see [Workspace::module].
+pub(crate) struct Workspace {
+    dir: TempDir,
+}
+
+impl Workspace {
+    /// Creates a workspace with the crates in the directories `members`,
+    /// which are `[workspace.dependencies]` as well, e.g. for a types crate.
+    /// The lockfile of this workspace pins their dependencies.
+    pub(crate) fn new(members: &[&str]) -> Self {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+        let workspace = Self {
+            dir: tempfile::tempdir().unwrap(),
+        };
+        let mut manifest = format!(
+            r#"[workspace]
+resolver = "2"
+members = {members:?}
+
+[workspace.dependencies]
+everestrs = {{ path = {:?}, features = ["build_bazel", "fuzzing"] }}
+everestrs-build = {{ path = {:?} }}
+"#,
+            root.join("everestrs"),
+            root.join("everestrs-build"),
+        );
+        for member in members {
+            let package = member.replace('_', "-");
+            manifest.push_str(&format!("{package} = {{ path = {member:?} }}\n"));
+        }
+        workspace.write("Cargo.toml", &manifest);
+        fs::copy(root.join("Cargo.lock"), workspace.path().join("Cargo.lock")).unwrap();
+        workspace
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Writes `content` to `path` relative to the workspace.
+    pub(crate) fn write(&self, path: impl AsRef<Path>, content: &str) {
+        let path = self.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// Returns the directory the [Output::files] of a module crate in `name`
+    /// are written to, which [crate::codegen::emit] needs.
+    pub(crate) fn src(&self, name: &str) -> PathBuf {
+        self.path().join(name).join("src")
+    }
+
+    /// Writes the binary crate `name` which includes the generated code of
+    /// `out` into its root, followed by `main`. It depends on the crates
+    /// the `options` require, the types crate is expected in the member of
+    /// the same name, see [Workspace::types_crate]. The binary can create a detached runtime and dispatch
+    /// to the generated code, but not connect to EVerest.
+    pub(crate) fn module(&self, name: &str, out: &Output, options: &Options, main: &str) {
+        let mut dependencies = vec![
+            "everestrs = { workspace = true }".to_string(),
+            r#"serde = { version = "1.0", features = ["derive"] }"#.to_string(),
+            r#"serde_json = "1.0""#.to_string(),
+        ];
+        if let Some(types_crate) = &options.types_crate {
+            let package = types_crate.replace('_', "-");
+            dependencies.push(format!("{package} = {{ workspace = true }}"));
+        }
+        if options.json_schema {
+            dependencies.push(r#"schemars = "1""#.to_string());
+        }
+        if options.arbitrary {
+            dependencies.push(r#"proptest = "1""#.to_string());
+        }
+        self.write(
+            Path::new(name).join("Cargo.toml"),
+            &format!(
+                "[package]\nname = \"{name}\"\nversion = \"0.0.0\"\nedition = \"2021\"\n\n\
+                 [dependencies]\n{}\n",
+                dependencies.join("\n")
+            ),
+        );
+        for (path, code) in &out.files {
+            self.write(self.src(name).join(path), code);
+        }
+        self.write(
+            self.src(name).join("main.rs"),
+            &format!("include!(\"generated.rs\");\n{DETACHED_RUNTIME}\n{main}\n"),
+        );
+    }
+
+    /// Writes the types crate of the search `roots` for the `options` into
+    /// the member named after [Options::types_crate].
+    pub(crate) fn types_crate(&self, roots: Vec<SearchRoot>, options: &Options) {
+        let name = options.types_crate.as_deref().unwrap();
+        let out = crate::codegen::emit_types_crate(roots, options.clone()).unwrap();
+        for (path, code) in &out.files {
+            self.write(Path::new(name).join(path), code);
+        }
+        self.write(
+            Path::new(name).join("Cargo.toml"),
+            &crate::types_crate_manifest(name, options),
+        );
+    }
+
+    /// Runs cargo with `args` in the workspace and returns what it printed
+    /// to stdout. Panics with the output of cargo if it fails. All
+    /// workspaces share one target directory, so the runtime is only built
+    /// once.
+    pub(crate) fn cargo(&self, args: &[&str]) -> String {
+        let target_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/generated");
+        let output = Command::new(std::env::var_os("CARGO").unwrap_or("cargo".into()))
+            .args(args)
+            .current_dir(self.path())
+            .env("CARGO_TARGET_DIR", target_dir)
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        assert!(
+            output.status.success(),
+            "cargo {} failed:\n{stdout}\n{}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        stdout
+    }
+}
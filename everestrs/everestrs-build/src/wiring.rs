@@ -0,0 +1,106 @@
+//! Checks how an EVerest config wires up its modules, shared by the config
+//! linter and the graph.
+
+use crate::schema::config::Connection;
+use crate::schema::{Config, Manifest};
+use std::collections::BTreeMap;
+
+/// A problem with a requirement of an active module or with one of its
+/// connections.
+#[derive(Debug, Clone)]
+pub(crate) struct Problem<'a> {
+    pub module_id: &'a str,
+    pub requirement_id: &'a str,
+    /// The offending connection with its index, `None` if the problem is with
+    /// the requirement as a whole.
+    pub connection: Option<(usize, &'a Connection)>,
+    pub kind: ProblemKind<'a>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ProblemKind<'a> {
+    /// The manifest of the module declares no such requirement.
+    UndeclaredRequirement,
+    /// The connection points to a module which is not active.
+    MissingModule,
+    /// The connected module has no such implementation.
+    MissingImplementation,
+    /// The connected implementation provides another interface than
+    /// required.
+    WrongInterface {
+        provided: &'a str,
+        required: &'a str,
+    },
+    /// The requirement has fewer or more connections than the manifest
+    /// allows.
+    ConnectionCount { count: i64, min: i64, max: i64 },
+}
+
+/// Returns the problems with the connections of `config`, ordered by module.
+/// The `manifests` are keyed by module name; modules without one are
+/// skipped, as are connections to them.
+pub(crate) fn check<'a>(
+    config: &'a Config,
+    manifests: &'a BTreeMap<String, Manifest>,
+) -> Vec<Problem<'a>> {
+    let mut problems = Vec::new();
+    for (module_id, module) in &config.active_modules {
+        let Some(manifest) = manifests.get(&module.module) else {
+            continue;
+        };
+        for (requirement_id, connections) in &module.connections {
+            let problem = |connection, kind| Problem {
+                module_id,
+                requirement_id,
+                connection,
+                kind,
+            };
+            let Some(requirement) = manifest.requires.get(requirement_id) else {
+                problems.push(problem(None, ProblemKind::UndeclaredRequirement));
+                continue;
+            };
+            for connection in connections.iter().enumerate() {
+                let target = connection.1;
+                let Some(target_module) = config.active_modules.get(&target.module_id) else {
+                    problems.push(problem(Some(connection), ProblemKind::MissingModule));
+                    continue;
+                };
+                let Some(target_manifest) = manifests.get(&target_module.module) else {
+                    continue;
+                };
+                match target_manifest.provides.get(&target.implementation_id) {
+                    None => problems.push(problem(
+                        Some(connection),
+                        ProblemKind::MissingImplementation,
+                    )),
+                    Some(provides) if provides.interface != requirement.interface => {
+                        problems.push(problem(
+                            Some(connection),
+                            ProblemKind::WrongInterface {
+                                provided: &provides.interface,
+                                required: &requirement.interface,
+                            },
+                        ))
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        for (requirement_id, requirement) in &manifest.requires {
+            let count = module.connections.get(requirement_id).map_or(0, Vec::len) as i64;
+            // Both default to 1.
+            let min = requirement.min_connections.unwrap_or(1);
+            let max = requirement.max_connections.unwrap_or(1);
+            if count < min || count > max {
+                problems.push(Problem {
+                    module_id,
+                    requirement_id,
+                    connection: None,
+                    kind: ProblemKind::ConnectionCount { count, min, max },
+                });
+            }
+        }
+    }
+    problems
+}
@@ -0,0 +1,320 @@
+//! Generates the code for an EVerest module at compile time, as alternative
+//! to a build script which runs `everestrs_build::Builder` and includes
+//! `OUT_DIR/generated.rs`:
+//!
+//! ```ignore
+//! #[everestrs_macros::everest_module(manifest = "manifest.yaml", everest_core = "../everest-core")]
+//! mod generated {}
+//! ```
+//!
+//! Paths are relative to the directory of the `Cargo.toml` of the module.
+//! The crate is rebuilt whenever the manifest or any interface or types file
+//! it uses changes.
+
+use everestrs_build::Builder;
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, ToTokens};
+use std::path::PathBuf;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Expr, ExprLit, ItemMod, Lit, Meta, Token, Visibility};
+
+/// Replaces the empty `mod generated {}` with the code generated for the
+/// manifest.
+///
+/// Takes these arguments:
+///
+///   - `manifest = "..."`: The manifest, `manifest.yaml` by default.
+///   - `everest_core = "..."`: A search root, can be repeated. Earlier roots
+///     take precedence.
+///   - `prefix = "..."`: The installation prefix of EVerest, searched after
+///     all `everest_core` roots.
+///   - `root_module = "..."`: The absolute path of the annotated module,
+///     `crate::generated` by default. Must be given if the module is not at
+///     the crate root, e.g. `crate::foo::generated`.
+///   - `template_dir = "..."`: A directory with custom templates. Only the
+///     templates which exist are tracked, a new one is picked up with the next
+///     change to the crate.
+///   - `types_crate = "..."`: The crate to take the types from.
+///   - `command_args_structs`, `precise_integers`, `json_schema`,
+///     `arbitrary`: The switches of the same name on
+///     `everestrs_build::Builder`.
+///
+/// `split_modules` is not supported, since the macro writes no files. The
+/// visibility of the module is used for all generated items, its attributes
+/// are kept.
+#[proc_macro_attribute]
+pub fn everest_module(attr: TokenStream, item: TokenStream) -> TokenStream {
+    match expand(attr.into(), item.into()) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+#[derive(Default)]
+struct Args {
+    manifest: Option<String>,
+    everest_core: Vec<String>,
+    prefix: Option<String>,
+    root_module: Option<String>,
+    template_dir: Option<String>,
+    types_crate: Option<String>,
+    command_args_structs: bool,
+    precise_integers: bool,
+    json_schema: bool,
+    arbitrary: bool,
+}
+
+fn parse_args(attr: TokenStream2) -> syn::Result<Args> {
+    let mut args = Args::default();
+    for meta in Punctuated::<Meta, Token![,]>::parse_terminated.parse2(attr)? {
+        let name = meta.path().get_ident().map(|i| i.to_string());
+        match (name.as_deref(), &meta) {
+            (Some("command_args_structs"), Meta::Path(_)) => args.command_args_structs = true,
+            (Some("precise_integers"), Meta::Path(_)) => args.precise_integers = true,
+            (Some("json_schema"), Meta::Path(_)) => args.json_schema = true,
+            (Some("arbitrary"), Meta::Path(_)) => args.arbitrary = true,
+            (Some("split_modules"), _) => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "`split_modules` needs a build script, the macro writes no files",
+                ))
+            }
+            (
+                Some(
+                    key @ ("manifest" | "everest_core" | "prefix" | "root_module" | "template_dir"
+                    | "types_crate"),
+                ),
+                Meta::NameValue(nv),
+            ) => {
+                let Expr::Lit(ExprLit {
+                    lit: Lit::Str(value),
+                    ..
+                }) = &nv.value
+                else {
+                    return Err(syn::Error::new_spanned(&nv.value, "Expected a string"));
+                };
+                match key {
+                    "manifest" => args.manifest = Some(value.value()),
+                    "everest_core" => args.everest_core.push(value.value()),
+                    "prefix" => args.prefix = Some(value.value()),
+                    "root_module" => args.root_module = Some(value.value()),
+                    "template_dir" => args.template_dir = Some(value.value()),
+                    _ => args.types_crate = Some(value.value()),
+                }
+            }
+            _ => return Err(syn::Error::new_spanned(meta, "Unknown argument")),
+        }
+    }
+    Ok(args)
+}
+
+/// Returns `vis` the way the codegen expects it. Rendering the tokens would
+/// put spaces into `pub(crate)`.
+fn visibility(vis: &Visibility) -> Option<String> {
+    let path = |p: &syn::Path| p.to_token_stream().to_string().replace(' ', "");
+    match vis {
+        Visibility::Inherited => None,
+        Visibility::Public(_) => Some("pub".to_string()),
+        Visibility::Restricted(r) if r.in_token.is_some() => {
+            Some(format!("pub(in {})", path(&r.path)))
+        }
+        Visibility::Restricted(r) => Some(format!("pub({})", path(&r.path))),
+    }
+}
+
+fn expand(attr: TokenStream2, item: TokenStream2) -> syn::Result<TokenStream2> {
+    let args = parse_args(attr)?;
+    let module: ItemMod = syn::parse2(item)?;
+    if module.ident != "generated" {
+        return Err(syn::Error::new_spanned(
+            &module.ident,
+            "The module must be called `generated`, the generated code refers to it by name",
+        ));
+    }
+    if module
+        .content
+        .as_ref()
+        .is_some_and(|(_, items)| !items.is_empty())
+    {
+        return Err(syn::Error::new_spanned(&module, "The module must be empty"));
+    }
+
+    let manifest_dir = PathBuf::from(
+        std::env::var("CARGO_MANIFEST_DIR")
+            .map_err(|_| syn::Error::new(Span::call_site(), "CARGO_MANIFEST_DIR is not set"))?,
+    );
+    let manifest = manifest_dir.join(args.manifest.as_deref().unwrap_or("manifest.yaml"));
+    let everest_core = args
+        .everest_core
+        .iter()
+        .map(|root| manifest_dir.join(root))
+        .collect();
+    let mut builder = Builder::new(manifest, everest_core)
+        .command_args_structs(args.command_args_structs)
+        .precise_integers(args.precise_integers)
+        .json_schema(args.json_schema)
+        .arbitrary(args.arbitrary);
+    if let Some(prefix) = args.prefix {
+        builder = builder.prefix(manifest_dir.join(prefix));
+    }
+    if let Some(path) = args.root_module {
+        builder = builder.root_module(path);
+    }
+    if let Some(dir) = args.template_dir {
+        builder = builder.template_dir(manifest_dir.join(dir));
+    }
+    if let Some(name) = args.types_crate {
+        builder = builder.types_crate(name);
+    }
+    if let Some(vis) = visibility(&module.vis) {
+        builder = builder.visibility(vis);
+    }
+
+    // Stable procedural macros cannot emit warnings, so we only report
+    // errors.
+    let out = builder
+        .emit()
+        .map_err(|err| syn::Error::new(Span::call_site(), format!("{err:#}")))?;
+    let mut code: ItemMod = syn::parse_str(out.root())?;
+    // The attributes of the annotated module, e.g. docs, `#[cfg]` or
+    // `#[allow]`, apply to the generated one.
+    code.attrs.splice(0..0, module.attrs);
+
+    // Including the inputs makes the compiler track them, so that the crate
+    // is rebuilt if they change.
+    let inputs = out
+        .input_files
+        .iter()
+        .map(|path| path.display().to_string());
+    Ok(quote! {
+        #code
+
+        const _: () = {
+            #( let _ = include_bytes!(#inputs); )*
+        };
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// A search root with the `counter` interface and its manifest.
+    fn everest_core() -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("interfaces")).unwrap();
+        fs::write(
+            dir.path().join("interfaces/counter.yaml"),
+            "description: Counts\nvars:\n  count:\n    description: The count\n    type: integer\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("manifest.yaml"),
+            "description: Counts\nprovides:\n  main:\n    interface: counter\n    description: The counter\nmetadata:\n  license: https://opensource.org/licenses/Apache-2.0\n  authors: [Jane Doe]\n",
+        )
+        .unwrap();
+        dir
+    }
+
+    /// Returns the arguments which point the macro to `dir`, followed by
+    /// `extra` ones.
+    fn attr(dir: &TempDir, extra: &str) -> TokenStream2 {
+        let root = dir.path().display().to_string();
+        let manifest = dir.path().join("manifest.yaml").display().to_string();
+        format!("manifest = {manifest:?}, everest_core = {root:?}{extra}")
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn keeps_the_attributes_and_visibility() {
+        let dir = everest_core();
+        let item = quote! {
+            /// The bindings.
+            #[cfg(all())]
+            #[allow(missing_docs)]
+            pub mod generated {}
+        };
+        let input: ItemMod = syn::parse2(item.clone()).unwrap();
+        let tokens = expand(attr(&dir, ", precise_integers"), item).unwrap();
+        let file: syn::File = syn::parse2(tokens).unwrap();
+        let syn::Item::Mod(module) = &file.items[0] else {
+            panic!("Expected the module first");
+        };
+        assert_eq!(module.ident, "generated");
+        assert!(matches!(module.vis, Visibility::Public(_)));
+        let attrs = |attrs: &[syn::Attribute]| -> Vec<String> {
+            attrs
+                .iter()
+                .map(|attr| attr.to_token_stream().to_string())
+                .collect()
+        };
+        let generated = attrs(&module.attrs);
+        assert_eq!(generated[..3], attrs(&input.attrs));
+        // The attributes of the generated module follow.
+        assert!(
+            matches!(module.attrs[3].style, syn::AttrStyle::Inner(_)),
+            "{generated:?}"
+        );
+        let code = module.to_token_stream().to_string();
+        assert!(code.contains("trait CounterServiceSubscriber"), "{code}");
+    }
+
+    #[test]
+    fn reports_errors() {
+        let dir = everest_core();
+        for (extra, item, error) in [
+            (
+                "",
+                quote!(
+                    mod bindings {}
+                ),
+                "The module must be called `generated`",
+            ),
+            (
+                "",
+                quote!(
+                    mod generated {
+                        fn foo() {}
+                    }
+                ),
+                "The module must be empty",
+            ),
+            (
+                ", unknown",
+                quote!(
+                    mod generated {}
+                ),
+                "Unknown argument",
+            ),
+            (
+                ", types_crate = 1",
+                quote!(
+                    mod generated {}
+                ),
+                "Expected a string",
+            ),
+            (
+                ", split_modules",
+                quote!(
+                    mod generated {}
+                ),
+                "`split_modules` needs a build script",
+            ),
+            (
+                ", root_module = \"crate::foo\"",
+                quote!(
+                    mod generated {}
+                ),
+                "Unsupported root module `crate::foo`",
+            ),
+        ] {
+            let err = expand(attr(&dir, extra), item).unwrap_err().to_string();
+            assert!(err.starts_with(error), "{extra}: {err}");
+        }
+    }
+}
@@ -51,11 +51,10 @@ fn find_libs_in_everest_framework(root: &Path) -> Option<Libraries> {
     }
 }
 
-/// Returns the Libraries path if this is an EVerest workspace where make install was run in
-/// everest-core/build or None if not.
-fn find_libs_in_everest_core_build_dist(root: &Path) -> Option<Libraries> {
-    let everestrs_sys = root.join("everest-core/build/dist/lib/libeverestrs_sys.a");
-    let framework = root.join("everest-core/build/dist/lib/libframework.so");
+/// Returns the Libraries path if EVerest is installed under `prefix` or None if it is not.
+fn find_libs_in_prefix(prefix: &Path) -> Option<Libraries> {
+    let everestrs_sys = prefix.join("lib/libeverestrs_sys.a");
+    let framework = prefix.join("lib/libframework.so");
     if everestrs_sys.exists() && framework.exists() {
         Some(Libraries {
             everestrs_sys,
@@ -85,6 +84,12 @@ fn print_link_options(p: &Path) {
     println!("cargo:rustc-link-lib={}", libname_from_path(p));
 }
 
+/// Returns the Libraries path if this is an EVerest workspace where make install was run in
+/// everest-core/build or None if not.
+fn find_libs_in_everest_core_build_dist(root: &Path) -> Option<Libraries> {
+    find_libs_in_prefix(&root.join("everest-core/build/dist"))
+}
+
 fn find_libs(root: &Path) -> Libraries {
     let libs = find_libs_in_everest_core_build_dist(&root);
     if libs.is_some() {
@@ -102,8 +107,13 @@ fn main() {
         return;
     }
 
-    let root = find_everest_workspace_root();
-    let libs = find_libs(&root);
+    // An installed EVerest does not need a workspace at all.
+    println!("cargo:rerun-if-env-changed=EVEREST_RS_PREFIX");
+    let libs = match env::var("EVEREST_RS_PREFIX") {
+        Ok(prefix) => find_libs_in_prefix(Path::new(&prefix))
+            .expect("EVEREST_RS_PREFIX does not contain an EVerest installation"),
+        Err(_) => find_libs(&find_everest_workspace_root()),
+    };
 
     print_link_options(&libs.everestrs_sys);
     print_link_options(&libs.framework);
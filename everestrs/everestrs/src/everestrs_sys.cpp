@@ -3,6 +3,8 @@
 
 #include "utils/types.hpp"
 
+#include <everest/logging.hpp>
+
 #include <cstdlib>
 #include <stdexcept>
 #include <type_traits>
@@ -97,8 +99,14 @@ void Module::signal_ready(const Runtime& rt) const {
 
 void Module::provide_command(const Runtime& rt, rust::String implementation_id, rust::String name) const {
     handle_->provide_cmd(std::string(implementation_id), std::string(name), [&rt, implementation_id, name](json args) {
-        JsonBlob blob = rt.handle_command(implementation_id, name, json2blob(args));
-        return json::parse(blob.data.begin(), blob.data.end());
+        try {
+            JsonBlob blob = rt.handle_command(implementation_id, name, json2blob(args));
+            return json::parse(blob.data.begin(), blob.data.end());
+        } catch (const rust::Error& e) {
+            EVLOG_error << fmt::format("Command {}->{} failed: {}", std::string(implementation_id),
+                                       std::string(name), e.what());
+            return json(nullptr);
+        }
     });
 }
 
@@ -106,7 +114,12 @@ void Module::subscribe_variable(const Runtime& rt, rust::String implementation_i
     // TODO(hrapp): I am not sure how to model the multiple slots that could theoretically be here.
     const Requirement req(std::string(implementation_id), 0);
     handle_->subscribe_var(req, std::string(name), [&rt, implementation_id, name](json args) {
-        rt.handle_variable(implementation_id, name, json2blob(args));
+        try {
+            rt.handle_variable(implementation_id, name, json2blob(args));
+        } catch (const rust::Error& e) {
+            EVLOG_error << fmt::format("Variable {}->{} could not be handled: {}", std::string(implementation_id),
+                                       std::string(name), e.what());
+        }
     });
 }
 
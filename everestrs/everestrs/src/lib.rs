@@ -7,6 +7,7 @@ use std::convert::TryFrom;
 use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
+use std::sync::PoisonError;
 use std::sync::RwLock;
 use std::sync::Weak;
 use thiserror::Error;
@@ -19,10 +20,24 @@ pub enum Error {
     InvalidArgument(&'static str),
     #[error("Mismatched type: Variant contains '{0}'")]
     MismatchedType(String),
+    #[error("the runtime is not connected to EVerest")]
+    Detached,
 }
 
 pub type Result<T> = ::std::result::Result<T, Error>;
 
+/// Why a call from EVerest could not be handled. The C++ side logs it, so an
+/// invalid payload or a failing handler does not unwind across the FFI.
+#[derive(Error, Debug)]
+enum DispatchError {
+    #[error("no subscriber is set")]
+    NoSubscriber,
+    #[error("invalid JSON payload: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Subscriber(#[from] Error),
+}
+
 #[cxx::bridge]
 mod ffi {
     extern "Rust" {
@@ -32,8 +47,13 @@ mod ffi {
             implementation_id: &str,
             name: &str,
             json: JsonBlob,
-        ) -> JsonBlob;
-        fn handle_variable(self: &Runtime, implementation_id: &str, name: &str, json: JsonBlob);
+        ) -> Result<JsonBlob>;
+        fn handle_variable(
+            self: &Runtime,
+            implementation_id: &str,
+            name: &str,
+            json: JsonBlob,
+        ) -> Result<()>;
         fn on_ready(&self);
     }
 
@@ -147,9 +167,8 @@ impl ffi::JsonBlob {
         &self.data
     }
 
-    fn deserialize<T: DeserializeOwned>(self) -> T {
-        // TODO(hrapp): Error handling
-        serde_json::from_slice(self.as_bytes()).unwrap()
+    fn deserialize<T: DeserializeOwned>(self) -> serde_json::Result<T> {
+        serde_json::from_slice(self.as_bytes())
     }
 
     fn from_vec(data: Vec<u8>) -> Self {
@@ -219,72 +238,107 @@ pub struct Runtime {
 }
 
 impl Runtime {
-    fn on_ready(&self) {
+    /// Returns the [Subscriber] unless none is set or it was dropped.
+    fn subscriber(&self) -> ::std::result::Result<Arc<dyn Subscriber>, DispatchError> {
         self.sub_impl
             .read()
-            .unwrap()
+            .unwrap_or_else(PoisonError::into_inner)
             .as_ref()
-            .unwrap()
-            .upgrade()
-            .unwrap()
-            .on_ready();
+            .and_then(Weak::upgrade)
+            .ok_or(DispatchError::NoSubscriber)
+    }
+
+    fn on_ready(&self) {
+        if let Ok(subscriber) = self.subscriber() {
+            subscriber.on_ready();
+        }
     }
 
-    fn handle_command(&self, impl_id: &str, name: &str, json: ffi::JsonBlob) -> ffi::JsonBlob {
+    fn handle_command(
+        &self,
+        impl_id: &str,
+        name: &str,
+        json: ffi::JsonBlob,
+    ) -> ::std::result::Result<ffi::JsonBlob, DispatchError> {
         let blob = self
-            .sub_impl
-            .read()
-            .unwrap()
-            .as_ref()
-            .unwrap()
-            .upgrade()
-            .unwrap()
-            .handle_command(impl_id, name, json.deserialize())
-            .unwrap();
-        ffi::JsonBlob::from_vec(serde_json::to_vec(&blob).unwrap())
+            .subscriber()?
+            .handle_command(impl_id, name, json.deserialize()?)?;
+        Ok(ffi::JsonBlob::from_vec(serde_json::to_vec(&blob)?))
     }
 
-    fn handle_variable(&self, impl_id: &str, name: &str, json: ffi::JsonBlob) {
-        self.sub_impl
-            .read()
-            .unwrap()
-            .as_ref()
-            .unwrap()
-            .upgrade()
-            .unwrap()
-            .handle_variable(impl_id, name, json.deserialize())
-            .unwrap();
+    fn handle_variable(
+        &self,
+        impl_id: &str,
+        name: &str,
+        json: ffi::JsonBlob,
+    ) -> ::std::result::Result<(), DispatchError> {
+        self.subscriber()?
+            .handle_variable(impl_id, name, json.deserialize()?)?;
+        Ok(())
     }
 
+    /// Publishes `message` as the variable `var_name` of `impl_id`.
+    ///
+    /// Panics if the runtime is not connected to EVerest, see
+    /// [Runtime::try_publish_variable] for the fallible variant.
     pub fn publish_variable<T: serde::Serialize>(
         &self,
         impl_id: &str,
         var_name: &str,
         message: &T,
     ) {
+        self.try_publish_variable(impl_id, var_name, message)
+            .expect("Publishing a variable failed.")
+    }
+
+    /// Publishes `message` as the variable `var_name` of `impl_id`, or
+    /// returns [Error::Detached] if the runtime is not connected to EVerest.
+    pub fn try_publish_variable<T: serde::Serialize>(
+        &self,
+        impl_id: &str,
+        var_name: &str,
+        message: &T,
+    ) -> Result<()> {
+        let cpp_module = self.cpp_module.as_ref().ok_or(Error::Detached)?;
         let blob = ffi::JsonBlob::from_vec(
             serde_json::to_vec(&message).expect("Serialization of data cannot fail."),
         );
-        (self.cpp_module)
-            .as_ref()
-            .unwrap()
-            .publish_variable(impl_id, var_name, blob);
+        cpp_module.publish_variable(impl_id, var_name, blob);
+        Ok(())
     }
 
+    /// Calls the command `name` of `impl_id` with `args` and returns its
+    /// result.
+    ///
+    /// Panics if the runtime is not connected to EVerest or the result does
+    /// not deserialize into `R`, see [Runtime::try_call_command] for the
+    /// fallible variant.
     pub fn call_command<T: serde::Serialize, R: serde::de::DeserializeOwned>(
         &self,
         impl_id: &str,
         name: &str,
         args: &T,
     ) -> R {
+        self.try_call_command(impl_id, name, args)
+            .expect("Calling a command failed.")
+    }
+
+    /// Calls the command `name` of `impl_id` with `args` and returns its
+    /// result, or returns an error if the runtime is not connected to EVerest
+    /// ([Error::Detached]) or the result does not deserialize into `R`
+    /// ([Error::MismatchedType]).
+    pub fn try_call_command<T: serde::Serialize, R: serde::de::DeserializeOwned>(
+        &self,
+        impl_id: &str,
+        name: &str,
+        args: &T,
+    ) -> Result<R> {
+        let cpp_module = self.cpp_module.as_ref().ok_or(Error::Detached)?;
         let blob = ffi::JsonBlob::from_vec(
             serde_json::to_vec(args).expect("Serialization of data cannot fail."),
         );
-        let return_value = (self.cpp_module)
-            .as_ref()
-            .unwrap()
-            .call_command(impl_id, name, blob);
-        serde_json::from_slice(&return_value.data).unwrap()
+        let return_value = cpp_module.call_command(impl_id, name, blob);
+        serde_json::from_slice(&return_value.data).map_err(|e| Error::MismatchedType(e.to_string()))
     }
 
     // TODO(hrapp): This function could use some error handling.
@@ -302,16 +356,33 @@ impl Runtime {
         })
     }
 
+    /// Creates a [Runtime] which is not connected to EVerest, for the
+    /// publishers passed to the dispatchers in fuzz targets. Publishing a
+    /// variable or calling a command on it fails with [Error::Detached], or
+    /// panics for the variants which do not return a [Result].
+    #[cfg(feature = "fuzzing")]
+    #[doc(hidden)]
+    pub fn detached() -> Pin<Arc<Self>> {
+        Arc::pin(Self {
+            cpp_module: cxx::UniquePtr::null(),
+            sub_impl: RwLock::new(None),
+        })
+    }
+
     pub fn set_subscriber(self: Pin<&Self>, sub_impl: Weak<dyn Subscriber>) {
         *self.sub_impl.write().unwrap() = Some(sub_impl);
         let manifest_json = self.cpp_module.as_ref().unwrap().initialize();
-        let manifest: schema::Manifest = manifest_json.deserialize();
+        let manifest: schema::Manifest = manifest_json
+            .deserialize()
+            .expect("EVerest passed an invalid manifest");
 
         // Implement all commands for all of our implementations, dispatch everything to the
         // Subscriber.
         for (implementation_id, implementation) in manifest.provides {
             let interface_s = self.cpp_module.get_interface(&implementation.interface);
-            let interface: schema::Interface = interface_s.deserialize();
+            let interface: schema::Interface = interface_s
+                .deserialize()
+                .expect("EVerest passed an invalid interface");
             for (name, _) in interface.cmds {
                 self.cpp_module.as_ref().unwrap().provide_command(
                     self,
@@ -325,7 +396,9 @@ impl Runtime {
         // TODO(hrapp): This looks very similar to the block above.
         for (implementation_id, provides) in manifest.requires {
             let interface_s = self.cpp_module.get_interface(&provides.interface);
-            let interface: schema::Interface = interface_s.deserialize();
+            let interface: schema::Interface = interface_s
+                .deserialize()
+                .expect("EVerest passed an invalid interface");
             for (name, _) in interface.vars {
                 self.cpp_module.as_ref().unwrap().subscribe_variable(
                     self,
@@ -392,6 +465,61 @@ impl TryFrom<&Config> for i64 {
     }
 }
 
+/// Integers which can be range checked by [deserialize_bounded].
+pub trait BoundedInteger {
+    /// Returns the first value outside of `min..=max`, if any.
+    fn out_of_range(&self, min: i128, max: i128) -> Option<i128>;
+}
+
+macro_rules! impl_bounded_integer {
+    ($($t:ty),*) => {
+        $(
+            impl BoundedInteger for $t {
+                fn out_of_range(&self, min: i128, max: i128) -> Option<i128> {
+                    let v = i128::from(*self);
+                    (v < min || v > max).then_some(v)
+                }
+            }
+        )*
+    };
+}
+
+impl_bounded_integer!(u8, i8, u16, i16, u32, i32, u64, i64);
+
+impl<T: BoundedInteger> BoundedInteger for Option<T> {
+    fn out_of_range(&self, min: i128, max: i128) -> Option<i128> {
+        self.as_ref().and_then(|v| v.out_of_range(min, max))
+    }
+}
+
+/// The bounds of an array apply to every item.
+impl<T: BoundedInteger> BoundedInteger for Vec<T> {
+    fn out_of_range(&self, min: i128, max: i128) -> Option<i128> {
+        self.iter().find_map(|v| v.out_of_range(min, max))
+    }
+}
+
+/// Deserializes an integer, or an array of them, and rejects it if a value
+/// is outside of `MIN..=MAX`.
+///
+/// The generated code uses this for integers whose schema bounds are narrower
+/// than the range of their Rust type.
+pub fn deserialize_bounded<'de, D, T, const MIN: i128, const MAX: i128>(
+    deserializer: D,
+) -> std::result::Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: serde::Deserialize<'de> + BoundedInteger,
+{
+    let value = T::deserialize(deserializer)?;
+    match value.out_of_range(MIN, MAX) {
+        Some(v) => Err(<D::Error as serde::de::Error>::custom(format!(
+            "integer `{v}` is out of range, expected {MIN}..={MAX}"
+        ))),
+        None => Ok(value),
+    }
+}
+
 /// Interface for fetching the configurations through the C++ runtime.
 pub fn get_module_configs() -> HashMap<String, HashMap<String, Config>> {
     let args: Args = argh::from_env();